@@ -0,0 +1,4 @@
+package prover
+
+// ProjectKey exposes projectKey to the prover_test package.
+var ProjectKey = projectKey
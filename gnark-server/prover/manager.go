@@ -2,6 +2,7 @@ package prover
 
 import (
 	"crypto/sha256"
+	"encoding/binary"
 	"fmt"
 	"math/big"
 	"sync"
@@ -39,9 +40,19 @@ func (p *ProverManager) NewProject(projectID string, projectVersion string, bina
 	return nil
 }
 
+// projectKey hashes each field behind its length, so ("a", "bc") and ("ab", "c") get different
+// keys. It must stay in step with ProjectKey in risc0-server.
 func projectKey(projectID string, projectVersion string) [32]byte {
-	data := projectID + projectVersion
-	return sha256.Sum256([]byte(data))
+	h := sha256.New()
+	for _, field := range []string{projectID, projectVersion} {
+		var size [8]byte
+		binary.LittleEndian.PutUint64(size[:], uint64(len(field)))
+		h.Write(size[:])
+		h.Write([]byte(field))
+	}
+	var key [32]byte
+	copy(key[:], h.Sum(nil))
+	return key
 }
 
 // Execute a proving task on prover identified by projectID with payloads as witness.
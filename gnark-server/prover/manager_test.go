@@ -1,6 +1,7 @@
 package prover_test
 
 import (
+	"encoding/hex"
 	"testing"
 
 	"github.com/consensys/gnark-crypto/ecc"
@@ -11,6 +12,12 @@ import (
 	"github.com/stretchr/testify/require"
 )
 
+func TestProjectKey(t *testing.T) {
+	// The same vector is checked by test_project_key_no_collision in risc0-server.
+	key := prover.ProjectKey("a", "bc")
+	require.Equal(t, "9a8acca1b6c6c0befd3fbc756aed625da998c998f7252e738c4ef061906b9b21", hex.EncodeToString(key[:]))
+}
+
 func TestProverManager(t *testing.T) {
 	circuitData, pkData, wit, _ := setupaddCircuit(t)
 	manager := &prover.ProverManager{}
@@ -40,6 +47,21 @@ func TestProverManager(t *testing.T) {
 		require.NotEmpty(t, proof)
 	})
 
+	t.Run("project keys do not collide", func(t *testing.T) {
+		err := manager.NewProject("a", "bc", circuitData, pkData)
+		require.NoError(t, err)
+
+		witBytes, err := wit.MarshalBinary()
+		require.NoError(t, err)
+
+		// Same concatenation, different pair.
+		_, err = manager.Exec("ab", "c", witBytes)
+		require.Error(t, err)
+
+		_, err = manager.Exec("a", "bc", witBytes)
+		require.NoError(t, err)
+	})
+
 	t.Run("exec non-existent project", func(t *testing.T) {
 		wit, _ := witness.New(ecc.BN254.ScalarField())
 		witBytes, _ := wit.MarshalBinary()
@@ -0,0 +1,365 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use dotenvy::dotenv;
+use rust_grpc::auth::AuthSettings;
+use rust_grpc::tls::TlsConfig;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::core::bonsai::BonsaiConfig;
+use crate::core::output::GUEST_OUTPUT_LIMIT;
+use crate::core::prover::SESSION_LIMIT;
+use crate::core::retry::RetryPolicy;
+
+const REDACTED: &str = "<redacted>";
+
+/// Server settings, read from a TOML file and overridden by environment
+/// variables.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen: String,
+    pub log: LogConfig,
+    pub limits: Limits,
+    pub retry: RetryConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bonsai: Option<BonsaiSettings>,
+    pub projects: ProjectsConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    pub auth: AuthSettings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracing: Option<TracingConfig>,
+    pub shutdown: ShutdownConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            listen: "0.0.0.0:4001".to_string(),
+            log: LogConfig::default(),
+            limits: Limits::default(),
+            retry: RetryConfig::default(),
+            bonsai: None,
+            projects: ProjectsConfig::default(),
+            tls: None,
+            auth: AuthSettings::default(),
+            metrics: None,
+            tracing: None,
+            shutdown: ShutdownConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// A `tracing` filter such as `info` or `risc0_server=debug,info`.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Guest cycles allowed per session.
+    pub session_limit: u64,
+    /// Bytes of guest stdout and stderr kept per stream.
+    pub guest_output_limit: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            session_limit: SESSION_LIMIT,
+            guest_output_limit: GUEST_OUTPUT_LIMIT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        RetryConfig {
+            max_attempts: policy.max_attempts,
+            initial_backoff_ms: policy.initial_backoff.as_millis() as u64,
+            max_backoff_ms: policy.max_backoff.as_millis() as u64,
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            initial_backoff: Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: Duration::from_millis(self.max_backoff_ms),
+            ..RetryPolicy::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BonsaiSettings {
+    pub api_url: String,
+    pub api_key: String,
+    pub poll_timeout_secs: u64,
+    /// Directory where in-progress sessions are saved so tasks retried after a
+    /// restart resume them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_dir: Option<PathBuf>,
+}
+
+impl Default for BonsaiSettings {
+    fn default() -> Self {
+        let defaults = BonsaiConfig::new("", "");
+        BonsaiSettings {
+            api_url: String::new(),
+            api_key: String::new(),
+            poll_timeout_secs: defaults.poll_timeout.as_secs(),
+            session_dir: None,
+        }
+    }
+}
+
+impl BonsaiSettings {
+    pub fn build(&self) -> Result<BonsaiConfig> {
+        if self.api_url.is_empty() || self.api_key.is_empty() {
+            bail!("bonsai.api_url and bonsai.api_key must both be set");
+        }
+        let mut config = BonsaiConfig::new(&self.api_url, &self.api_key);
+        config.poll_timeout = Duration::from_secs(self.poll_timeout_secs);
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectsConfig {
+    /// Versions kept per project; older ones are dropped on registration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_versions: Option<usize>,
+    /// JSON file mapping project IDs to owner addresses allowed to upload.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owners_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Address of the Prometheus `/metrics` HTTP endpoint.
+    pub listen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TracingConfig {
+    /// OTLP/gRPC collector spans are exported to, e.g. `http://otel:4317`.
+    pub otlp_endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+fn default_service_name() -> String {
+    "risc0-server".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    /// How long running tasks get to finish after SIGTERM.
+    pub grace_period_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        ShutdownConfig {
+            grace_period_secs: 60,
+        }
+    }
+}
+
+impl Config {
+    /// Reads `path` if given, then applies environment overrides (including a
+    /// `.env` file).
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        dotenv().ok();
+
+        let mut config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config {}", path.display()))?;
+                toml::from_str(&text)
+                    .with_context(|| format!("Failed to parse config {}", path.display()))?
+            }
+            None => Config::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        parse_env("SESSION_LIMIT", &mut self.limits.session_limit)?;
+        parse_env("GUEST_OUTPUT_LIMIT", &mut self.limits.guest_output_limit)?;
+
+        parse_env("PROVE_RETRY_MAX_ATTEMPTS", &mut self.retry.max_attempts)?;
+        parse_env(
+            "PROVE_RETRY_INITIAL_BACKOFF_MS",
+            &mut self.retry.initial_backoff_ms,
+        )?;
+        parse_env("PROVE_RETRY_MAX_BACKOFF_MS", &mut self.retry.max_backoff_ms)?;
+
+        let api_url = env::var("BONSAI_API_URL").ok();
+        let api_key = env::var("BONSAI_API_KEY").ok();
+        if api_url.is_some() || api_key.is_some() {
+            let bonsai = self.bonsai.get_or_insert_with(BonsaiSettings::default);
+            if let Some(api_url) = api_url {
+                bonsai.api_url = api_url;
+            }
+            if let Some(api_key) = api_key {
+                bonsai.api_key = api_key;
+            }
+        }
+        if let Some(bonsai) = &mut self.bonsai {
+            parse_env("BONSAI_POLL_TIMEOUT_SECS", &mut bonsai.poll_timeout_secs)?;
+            if let Ok(v) = env::var("BONSAI_SESSION_DIR") {
+                bonsai.session_dir = Some(v.into());
+            }
+        }
+
+        if let Ok(v) = env::var("PROJECT_KEEP_VERSIONS") {
+            self.projects.keep_versions = Some(
+                v.parse()
+                    .context("PROJECT_KEEP_VERSIONS must be a number")?,
+            );
+        }
+        if let Ok(v) = env::var("PROJECT_OWNERS_FILE") {
+            self.projects.owners_file = Some(v.into());
+        }
+
+        if let Some(tls) = TlsConfig::from_env()? {
+            self.tls = Some(tls);
+        }
+        self.auth.apply_env();
+
+        parse_env(
+            "SHUTDOWN_GRACE_PERIOD_SECS",
+            &mut self.shutdown.grace_period_secs,
+        )?;
+
+        if let Ok(listen) = env::var("METRICS_LISTEN") {
+            self.metrics = Some(MetricsConfig { listen });
+        }
+
+        if let Ok(otlp_endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            let tracing = self.tracing.get_or_insert_with(|| TracingConfig {
+                otlp_endpoint: String::new(),
+                service_name: default_service_name(),
+            });
+            tracing.otlp_endpoint = otlp_endpoint;
+        }
+        if let (Some(tracing), Ok(name)) = (&mut self.tracing, env::var("OTEL_SERVICE_NAME")) {
+            tracing.service_name = name;
+        }
+        Ok(())
+    }
+
+    /// A copy safe to log, with credentials masked.
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if let Some(bonsai) = &mut config.bonsai {
+            bonsai.api_key = REDACTED.to_string();
+        }
+        config.auth = config.auth.redacted();
+        config
+    }
+
+    /// The effective config as TOML, with credentials masked.
+    pub fn display(&self) -> String {
+        toml::to_string(&self.redacted()).unwrap_or_else(|e| format!("<unprintable: {}>", e))
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str, value: &mut T) -> Result<()> {
+    if let Ok(v) = env::var(name) {
+        *value = v
+            .parse()
+            .map_err(|_| anyhow::anyhow!("{} must be a number", name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_redact() {
+        let config: Config = toml::from_str(
+            r#"
+            listen = "127.0.0.1:5000"
+
+            [limits]
+            session_limit = 100
+
+            [bonsai]
+            api_url = "https://api.bonsai.xyz"
+            api_key = "secret-key"
+
+            [tls]
+            cert = "server.pem"
+            key = "server.key"
+
+            [auth]
+            tokens = ["secret-token"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.listen, "127.0.0.1:5000");
+        assert_eq!(config.limits.session_limit, 100);
+        assert_eq!(config.limits.guest_output_limit, GUEST_OUTPUT_LIMIT);
+        assert_eq!(config.retry, RetryConfig::default());
+        assert!(config.bonsai.as_ref().unwrap().build().is_ok());
+        assert_eq!(config.tls.as_ref().unwrap().client_ca_path, None);
+
+        let shown = config.display();
+        assert!(!shown.contains("secret"), "{}", shown);
+        assert!(shown.contains("https://api.bonsai.xyz"));
+        assert_eq!(
+            toml::from_str::<Config>(&shown).unwrap().listen,
+            config.listen
+        );
+
+        assert!(toml::from_str::<Config>("unknown = 1").is_err());
+    }
+}
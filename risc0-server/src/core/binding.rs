@@ -0,0 +1,168 @@
+use anyhow::{anyhow, bail, Context, Result};
+use risc0_zkvm::serde::to_vec;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+use super::journal::{FieldType, JournalSchema};
+
+/// A rule tying a field of the task payload to what the guest committed, so a
+/// proof cannot be returned for other public inputs than the ones requested.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InputBinding {
+    /// The payload field must equal a field of the decoded journal.
+    Field { payload: String, journal: String },
+    /// The journal must start with the payload field, risc0-serde encoded as
+    /// `encoding`.
+    Prefix {
+        payload: String,
+        encoding: FieldType,
+    },
+}
+
+impl InputBinding {
+    /// Checks the rule can be evaluated for a project with `schema`.
+    pub fn validate(&self, schema: Option<&JournalSchema>) -> Result<()> {
+        if let InputBinding::Field { journal, .. } = self {
+            let schema = schema.context("field bindings need a journal_schema")?;
+            if !schema.fields.iter().any(|f| &f.name == journal) {
+                bail!("journal_schema has no field {}", journal);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn check(
+        &self,
+        payload: &Value,
+        journal: &[u8],
+        schema: Option<&JournalSchema>,
+    ) -> Result<()> {
+        match self {
+            InputBinding::Field {
+                payload: name,
+                journal: field,
+            } => {
+                let expected = payload_field(payload, name)?;
+                let decoded = schema
+                    .context("field bindings need a journal_schema")?
+                    .decode(journal)?;
+                let actual = decoded
+                    .get(field)
+                    .ok_or_else(|| anyhow!("journal has no field {}", field))?;
+                if scalar_string(expected) != scalar_string(actual) {
+                    bail!(
+                        "payload field {} is {} but journal field {} is {}",
+                        name,
+                        expected,
+                        field,
+                        actual
+                    );
+                }
+            }
+            InputBinding::Prefix {
+                payload: name,
+                encoding,
+            } => {
+                let expected = encode(*encoding, payload_field(payload, name)?)
+                    .with_context(|| format!("Failed to encode payload field {}", name))?;
+                if !journal.starts_with(&expected) {
+                    bail!("journal does not start with payload field {}", name);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn payload_field<'a>(payload: &'a Value, name: &str) -> Result<&'a Value> {
+    payload
+        .get(name)
+        .ok_or_else(|| anyhow!("payload has no field {}", name))
+}
+
+// Payload fields are often strings ("14") where the journal has numbers, so
+// values are compared by their plain text.
+fn scalar_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &Value) -> Result<T> {
+    scalar_string(value)
+        .parse()
+        .map_err(|_| anyhow!("{} is not a valid number", value))
+}
+
+fn encode(ty: FieldType, value: &Value) -> Result<Vec<u8>> {
+    let words = match ty {
+        FieldType::U32 => to_vec(&parse::<u32>(value)?)?,
+        FieldType::U64 => to_vec(&parse::<u64>(value)?)?,
+        FieldType::I32 => to_vec(&parse::<i32>(value)?)?,
+        FieldType::I64 => to_vec(&parse::<i64>(value)?)?,
+        FieldType::Bool => to_vec(&value.as_bool().context("expected a bool")?)?,
+        FieldType::String => to_vec(&scalar_string(value))?,
+        FieldType::Bytes => {
+            let s = value.as_str().context("expected a hex string")?;
+            to_vec(&hex::decode(s.trim_start_matches("0x"))?)?
+        }
+    };
+    Ok(bytemuck::cast_slice(&words).to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn journal<T: serde::Serialize>(value: &T) -> Vec<u8> {
+        bytemuck::cast_slice(&to_vec(value).unwrap()).to_vec()
+    }
+
+    #[test]
+    fn test_field_binding() {
+        let schema: JournalSchema = serde_json::from_value(json!([
+            {"name": "public_input", "type": "string"},
+            {"name": "result", "type": "u32"}
+        ]))
+        .unwrap();
+        let binding: InputBinding = serde_json::from_value(
+            json!({"kind": "field", "payload": "public_input", "journal": "public_input"}),
+        )
+        .unwrap();
+        binding.validate(Some(&schema)).unwrap();
+        assert!(binding.validate(None).is_err());
+
+        let bytes = journal(&("3,34".to_string(), 1u32));
+        let payload = json!({"public_input": "3,34"});
+        binding.check(&payload, &bytes, Some(&schema)).unwrap();
+
+        let payload = json!({"public_input": "3,35"});
+        assert!(binding.check(&payload, &bytes, Some(&schema)).is_err());
+        assert!(binding.check(&json!({}), &bytes, Some(&schema)).is_err());
+    }
+
+    #[test]
+    fn test_prefix_binding() {
+        let binding: InputBinding = serde_json::from_value(
+            json!({"kind": "prefix", "payload": "threshold", "encoding": "u32"}),
+        )
+        .unwrap();
+        let bytes = journal(&(11u32, "rest".to_string()));
+
+        binding
+            .check(&json!({"threshold": "11"}), &bytes, None)
+            .unwrap();
+        binding
+            .check(&json!({"threshold": 11}), &bytes, None)
+            .unwrap();
+        assert!(binding
+            .check(&json!({"threshold": 12}), &bytes, None)
+            .is_err());
+        assert!(binding
+            .check(&json!({"threshold": "x"}), &bytes, None)
+            .is_err());
+    }
+}
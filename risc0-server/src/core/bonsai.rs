@@ -0,0 +1,348 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use bonsai_sdk::{
+    blocking::{Client, SessionId, SnarkId},
+    SdkErr,
+};
+use risc0_zkvm::{Receipt, VERSION};
+use thiserror::Error;
+
+use super::session::BonsaiProgress;
+use crate::metrics::BONSAI_REQUEST_DURATION;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_POLL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+// Bonsai reports quota problems as plain error bodies, not dedicated codes.
+const QUOTA_MARKERS: [&str; 4] = ["quota", "budget", "concurrent", "too many requests"];
+
+#[derive(Debug, Error)]
+pub enum BonsaiError {
+    #[error("Bonsai quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[error("Bonsai {kind} {id} did not finish within {timeout:?}")]
+    Timeout {
+        kind: &'static str,
+        id: String,
+        timeout: Duration,
+    },
+    #[error("Bonsai {kind} {id} {status}: {message}")]
+    SessionFailed {
+        kind: &'static str,
+        id: String,
+        status: String,
+        message: String,
+    },
+    #[error("Bonsai request failed: {0}")]
+    Request(SdkErr),
+    #[error("Failed to decode Bonsai receipt: {0}")]
+    Receipt(#[from] bincode::Error),
+}
+
+impl From<SdkErr> for BonsaiError {
+    fn from(err: SdkErr) -> Self {
+        if let SdkErr::InternalServerErr(body) = &err {
+            let lower = body.to_lowercase();
+            if QUOTA_MARKERS.iter().any(|marker| lower.contains(marker)) {
+                return BonsaiError::QuotaExceeded(body.clone());
+            }
+        }
+        BonsaiError::Request(err)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BonsaiConfig {
+    pub api_url: String,
+    pub api_key: String,
+    pub poll_interval: Duration,
+    pub poll_timeout: Duration,
+}
+
+impl BonsaiConfig {
+    pub fn new(api_url: &str, api_key: &str) -> Self {
+        BonsaiConfig {
+            api_url: api_url.to_string(),
+            api_key: api_key.to_string(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+        }
+    }
+}
+
+/// Drives a Bonsai proof: image and input upload, session, SNARK conversion
+/// and receipt download.
+///
+/// The underlying HTTP client is blocking, so it must be created and used from
+/// a blocking thread.
+#[derive(Clone)]
+pub struct BonsaiClient {
+    client: Client,
+    poll_interval: Duration,
+    poll_timeout: Duration,
+    // Labels this client's request metrics; empty for calls made for no project.
+    project_id: String,
+}
+
+impl BonsaiClient {
+    pub fn new(config: &BonsaiConfig) -> Result<Self, BonsaiError> {
+        let client = Client::from_parts(config.api_url.clone(), config.api_key.clone(), VERSION)?;
+        Ok(BonsaiClient {
+            client,
+            poll_interval: config.poll_interval,
+            poll_timeout: config.poll_timeout,
+            project_id: String::new(),
+        })
+    }
+
+    /// Attributes this client's requests to `project_id` in the metrics.
+    pub fn with_project_id(mut self, project_id: &str) -> Self {
+        self.project_id = project_id.to_string();
+        self
+    }
+
+    /// Checks Bonsai is reachable and accepts the API key.
+    pub fn ping(&self) -> Result<(), BonsaiError> {
+        self.timed("version", || self.client.version())?;
+        Ok(())
+    }
+
+    /// Uploads an ELF under its image ID. Returns true if Bonsai already had it.
+    pub fn upload_image(&self, image_id: &str, elf: &[u8]) -> Result<bool, BonsaiError> {
+        Ok(self.timed("upload_image", || {
+            self.client.upload_img(image_id, elf.to_vec())
+        })?)
+    }
+
+    /// Proves `input` against an image previously sent with `upload_image`.
+    pub fn prove_groth16(&self, image_id: &str, input: Vec<u8>) -> Result<Receipt, BonsaiError> {
+        self.prove_groth16_resumable(image_id, input, None, |_| ())
+    }
+
+    /// Like `prove_groth16`, but continues from `resume` when given instead of
+    /// starting a session, and hands each new session or SNARK ID to
+    /// `checkpoint` so the proof can be resumed after a restart.
+    pub fn prove_groth16_resumable(
+        &self,
+        image_id: &str,
+        input: Vec<u8>,
+        resume: Option<BonsaiProgress>,
+        mut checkpoint: impl FnMut(&BonsaiProgress),
+    ) -> Result<Receipt, BonsaiError> {
+        let mut progress = match resume {
+            Some(progress) => progress,
+            None => {
+                let input_id = self.timed("upload_input", || self.client.upload_input(input))?;
+                let session = self.timed("create_session", || {
+                    self.client
+                        .create_session(image_id.to_string(), input_id, vec![], false)
+                })?;
+                let progress = BonsaiProgress {
+                    session: session.uuid,
+                    snark: None,
+                };
+                checkpoint(&progress);
+                progress
+            }
+        };
+
+        let snark = match &progress.snark {
+            Some(uuid) => SnarkId::new(uuid.clone()),
+            None => {
+                self.wait_session(&SessionId::new(progress.session.clone()))?;
+                let snark = self.timed("create_snark", || {
+                    self.client.create_snark(progress.session.clone())
+                })?;
+                progress.snark = Some(snark.uuid.clone());
+                checkpoint(&progress);
+                snark
+            }
+        };
+        let output = self.wait_snark(&snark)?;
+
+        let receipt_buf = self.timed("download", || self.client.download(&output))?;
+        Ok(bincode::deserialize(&receipt_buf)?)
+    }
+
+    fn wait_session(&self, session: &SessionId) -> Result<(), BonsaiError> {
+        let started = Instant::now();
+        loop {
+            let res = self.timed("session_status", || session.status(&self.client))?;
+            match res.status.as_str() {
+                "RUNNING" => self.sleep_or_timeout(started, "session", &session.uuid)?,
+                "SUCCEEDED" => return Ok(()),
+                status => {
+                    return Err(BonsaiError::SessionFailed {
+                        kind: "session",
+                        id: session.uuid.clone(),
+                        status: status.to_string(),
+                        message: res.error_msg.unwrap_or_default(),
+                    })
+                }
+            }
+        }
+    }
+
+    fn wait_snark(&self, snark: &SnarkId) -> Result<String, BonsaiError> {
+        let started = Instant::now();
+        loop {
+            let res = self.timed("snark_status", || snark.status(&self.client))?;
+            match res.status.as_str() {
+                "RUNNING" => self.sleep_or_timeout(started, "snark", &snark.uuid)?,
+                "SUCCEEDED" => {
+                    return res.output.ok_or_else(|| BonsaiError::SessionFailed {
+                        kind: "snark",
+                        id: snark.uuid.clone(),
+                        status: "SUCCEEDED".to_string(),
+                        message: "no output".to_string(),
+                    })
+                }
+                status => {
+                    return Err(BonsaiError::SessionFailed {
+                        kind: "snark",
+                        id: snark.uuid.clone(),
+                        status: status.to_string(),
+                        message: res.error_msg.unwrap_or_default(),
+                    })
+                }
+            }
+        }
+    }
+
+    fn sleep_or_timeout(
+        &self,
+        started: Instant,
+        kind: &'static str,
+        id: &str,
+    ) -> Result<(), BonsaiError> {
+        if started.elapsed() >= self.poll_timeout {
+            return Err(BonsaiError::Timeout {
+                kind,
+                id: id.to_string(),
+                timeout: self.poll_timeout,
+            });
+        }
+        thread::sleep(self.poll_interval);
+        Ok(())
+    }
+
+    /// Runs one Bonsai API call, recording its latency.
+    fn timed<T>(
+        &self,
+        operation: &str,
+        call: impl FnOnce() -> Result<T, SdkErr>,
+    ) -> Result<T, SdkErr> {
+        let started = Instant::now();
+        let result = call();
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        BONSAI_REQUEST_DURATION
+            .with_label_values(&[&self.project_id, operation, outcome])
+            .observe(started.elapsed().as_secs_f64());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::bonsai_mock::{groth16_receipt, MockBonsai, MockMode};
+
+    async fn prove_with(mock: &MockBonsai) -> Result<Receipt, BonsaiError> {
+        let config = mock.config();
+        tokio::task::spawn_blocking(move || {
+            let client = BonsaiClient::new(&config)?;
+            client.upload_image("image", b"elf")?;
+            client.prove_groth16("image", vec![1, 2, 3, 4])
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_prove_groth16() {
+        let fixture = groth16_receipt();
+        let mock = MockBonsai::start(fixture.clone()).await;
+
+        let receipt = prove_with(&mock).await.unwrap();
+        assert_eq!(receipt.journal, fixture.journal);
+    }
+
+    #[tokio::test]
+    async fn test_quota_exceeded() {
+        let mock = MockBonsai::start(groth16_receipt()).await;
+        mock.set_mode(MockMode::QuotaExceeded);
+
+        let err = prove_with(&mock).await.unwrap_err();
+        assert!(matches!(err, BonsaiError::QuotaExceeded(_)), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_session_failed() {
+        let mock = MockBonsai::start(groth16_receipt()).await;
+        mock.set_mode(MockMode::SessionFails);
+
+        let err = prove_with(&mock).await.unwrap_err();
+        assert!(
+            matches!(&err, BonsaiError::SessionFailed { status, .. } if status == "FAILED"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_timeout() {
+        let mock = MockBonsai::start(groth16_receipt()).await;
+        mock.set_mode(MockMode::Stuck);
+
+        let mut config = mock.config();
+        config.poll_timeout = Duration::from_millis(50);
+        let err = tokio::task::spawn_blocking(move || {
+            let client = BonsaiClient::new(&config)?;
+            client.upload_image("image", b"elf")?;
+            client.prove_groth16("image", vec![])
+        })
+        .await
+        .unwrap()
+        .unwrap_err();
+        assert!(matches!(err, BonsaiError::Timeout { .. }), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_resume_session() {
+        let fixture = groth16_receipt();
+        let mock = MockBonsai::start(fixture.clone()).await;
+        mock.set_mode(MockMode::Stuck);
+
+        // The first run stops mid-session, as if the server had been killed.
+        let mut config = mock.config();
+        config.poll_timeout = Duration::from_millis(50);
+        let (err, saved) = tokio::task::spawn_blocking(move || {
+            let client = BonsaiClient::new(&config).unwrap();
+            client.upload_image("image", b"elf").unwrap();
+            let mut saved = None;
+            let err = client
+                .prove_groth16_resumable("image", vec![], None, |p| saved = Some(p.clone()))
+                .unwrap_err();
+            (err, saved)
+        })
+        .await
+        .unwrap();
+        assert!(matches!(err, BonsaiError::Timeout { .. }), "{}", err);
+        let saved = saved.unwrap();
+        assert_eq!(saved.snark, None);
+
+        mock.set_mode(MockMode::Normal);
+        let config = mock.config();
+        let receipt = tokio::task::spawn_blocking(move || {
+            let client = BonsaiClient::new(&config).unwrap();
+            client.prove_groth16_resumable("image", vec![], Some(saved), |_| ())
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(receipt.journal, fixture.journal);
+        assert_eq!(mock.sessions(), 1);
+    }
+}
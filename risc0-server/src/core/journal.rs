@@ -0,0 +1,188 @@
+use anyhow::{bail, Context, Result};
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+
+const WORD_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    U32,
+    U64,
+    I32,
+    I64,
+    Bool,
+    String,
+    /// A `Vec<u8>`, which risc0 serde writes as a length followed by one word
+    /// per byte.
+    Bytes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JournalField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: FieldType,
+}
+
+/// The fields a guest commits, in order, as written by `env::commit` with
+/// risc0 serde.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct JournalSchema {
+    pub fields: Vec<JournalField>,
+}
+
+impl JournalSchema {
+    /// Decodes `journal` into a JSON object keyed by field name. u64/i64 values
+    /// are rendered as strings so they survive JSON consumers that use f64.
+    pub fn decode(&self, journal: &[u8]) -> Result<Value> {
+        if journal.len() % WORD_SIZE != 0 {
+            bail!("journal length {} is not word aligned", journal.len());
+        }
+        let mut reader = WordReader {
+            words: journal
+                .chunks_exact(WORD_SIZE)
+                .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+                .collect(),
+            pos: 0,
+        };
+
+        let mut object = Map::new();
+        for field in &self.fields {
+            let value = reader
+                .read(field.ty)
+                .with_context(|| format!("Failed to decode journal field {}", field.name))?;
+            object.insert(field.name.clone(), value);
+        }
+        if reader.pos != reader.words.len() {
+            bail!(
+                "journal has {} trailing words after the schema fields",
+                reader.words.len() - reader.pos
+            );
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+struct WordReader {
+    words: Vec<u32>,
+    pos: usize,
+}
+
+impl WordReader {
+    fn word(&mut self) -> Result<u32> {
+        let word = *self
+            .words
+            .get(self.pos)
+            .context("unexpected end of journal")?;
+        self.pos += 1;
+        Ok(word)
+    }
+
+    fn dword(&mut self) -> Result<u64> {
+        let low = self.word()? as u64;
+        let high = self.word()? as u64;
+        Ok(low | (high << 32))
+    }
+
+    fn read(&mut self, ty: FieldType) -> Result<Value> {
+        Ok(match ty {
+            FieldType::U32 => Value::from(self.word()?),
+            FieldType::I32 => Value::from(self.word()? as i32),
+            FieldType::U64 => Value::from(self.dword()?.to_string()),
+            FieldType::I64 => Value::from((self.dword()? as i64).to_string()),
+            FieldType::Bool => match self.word()? {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                w => bail!("invalid bool word {}", w),
+            },
+            FieldType::String => {
+                let len = self.word()? as usize;
+                let words = len.div_ceil(WORD_SIZE);
+                if self.pos + words > self.words.len() {
+                    bail!("string of {} bytes runs past the journal", len);
+                }
+                let bytes: Vec<u8> = self.words[self.pos..self.pos + words]
+                    .iter()
+                    .flat_map(|w| w.to_le_bytes())
+                    .take(len)
+                    .collect();
+                self.pos += words;
+                Value::from(String::from_utf8(bytes).context("string is not UTF-8")?)
+            }
+            FieldType::Bytes => {
+                let len = self.word()? as usize;
+                let mut bytes = Vec::with_capacity(len.min(self.words.len()));
+                for _ in 0..len {
+                    let w = self.word()?;
+                    bytes.push(u8::try_from(w).context("byte word out of range")?);
+                }
+                Value::from(hex::encode(bytes))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::serde::to_vec;
+
+    fn journal<T: serde::Serialize>(value: &T) -> Vec<u8> {
+        bytemuck::cast_slice(&to_vec(value).unwrap()).to_vec()
+    }
+
+    fn schema(json: &str) -> JournalSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = journal(&(
+            7u32,
+            u64::MAX,
+            -3i32,
+            true,
+            "I know your private input".to_string(),
+            vec![0xdeu8, 0xad],
+        ));
+        let schema = schema(
+            r#"[
+                {"name": "a", "type": "u32"},
+                {"name": "b", "type": "u64"},
+                {"name": "c", "type": "i32"},
+                {"name": "d", "type": "bool"},
+                {"name": "e", "type": "string"},
+                {"name": "f", "type": "bytes"}
+            ]"#,
+        );
+
+        let decoded = schema.decode(&bytes).unwrap();
+        assert_eq!(
+            decoded,
+            serde_json::json!({
+                "a": 7,
+                "b": u64::MAX.to_string(),
+                "c": -3,
+                "d": true,
+                "e": "I know your private input",
+                "f": "dead"
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_mismatch() {
+        let bytes = journal(&(1u32, 2u32));
+        assert!(schema(r#"[{"name": "a", "type": "u32"}]"#)
+            .decode(&bytes)
+            .is_err());
+        assert!(schema(r#"[{"name": "a", "type": "string"}]"#)
+            .decode(&bytes)
+            .is_err());
+        assert!(
+            serde_json::from_str::<JournalSchema>(r#"[{"name": "a", "type": "f32"}]"#).is_err()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+use super::binding::InputBinding;
+use super::journal::JournalSchema;
+use super::prover::GuestEnv;
+
+/// Project settings carried as JSON in `NewProjectRequest.metadata`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectMetadata {
+    /// Upload the image to Bonsai at registration, so Snark tasks only send
+    /// their inputs.
+    pub groth16: bool,
+    /// Environment variables set for the guest on every task.
+    pub env: BTreeMap<String, String>,
+    /// Per-deployment constants, written to the guest as a JSON string ahead
+    /// of the task payloads.
+    pub config: Option<Value>,
+    /// Layout of the committed journal, used to return it decoded as JSON.
+    pub journal_schema: Option<JournalSchema>,
+    /// Rules the journal must satisfy against the task payload before a proof
+    /// is returned.
+    pub input_bindings: Vec<InputBinding>,
+    /// Sample task payloads run once at registration; a guest that fails on
+    /// them is rejected. Strings are passed as-is, other values as JSON.
+    pub preflight_payloads: Vec<Value>,
+}
+
+impl ProjectMetadata {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.is_empty() {
+            return Ok(ProjectMetadata::default());
+        }
+        let metadata: ProjectMetadata =
+            serde_json::from_slice(bytes).context("Failed to parse project metadata")?;
+        metadata.validate()?;
+        Ok(metadata)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for name in self.env.keys() {
+            if name.is_empty() || name.contains('=') || name.contains('\0') {
+                bail!("invalid guest environment variable name {:?}", name);
+            }
+        }
+        if self.groth16 && !self.env.is_empty() {
+            bail!("Bonsai does not support guest environment variables");
+        }
+        for binding in &self.input_bindings {
+            binding
+                .validate(self.journal_schema.as_ref())
+                .context("invalid input binding")?;
+        }
+        Ok(())
+    }
+
+    pub fn preflight_data(&self) -> Vec<String> {
+        self.preflight_payloads
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            })
+            .collect()
+    }
+
+    pub fn guest_env(&self) -> GuestEnv {
+        GuestEnv {
+            vars: self.env.clone(),
+            config: self.config.as_ref().map(Value::to_string),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        assert!(!ProjectMetadata::from_bytes(b"").unwrap().groth16);
+        assert!(!ProjectMetadata::from_bytes(b"{}").unwrap().groth16);
+        assert!(
+            ProjectMetadata::from_bytes(br#"{"groth16": true}"#)
+                .unwrap()
+                .groth16
+        );
+        assert!(ProjectMetadata::from_bytes(b"not json").is_err());
+
+        let metadata = ProjectMetadata::from_bytes(
+            br#"{"journal_schema": [{"name": "message", "type": "string"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.journal_schema.unwrap().fields[0].name, "message");
+
+        // Field bindings must name a journal_schema field.
+        assert!(ProjectMetadata::from_bytes(
+            br#"{"input_bindings": [{"kind": "field", "payload": "a", "journal": "a"}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_preflight_data() {
+        let metadata = ProjectMetadata::from_bytes(
+            br#"{"preflight_payloads": [{"private_input": "14"}, "3,34"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.preflight_data(),
+            vec![r#"{"private_input":"14"}"#.to_string(), "3,34".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_guest_env() {
+        let metadata = ProjectMetadata::from_bytes(
+            br#"{"env": {"CHAIN_ID": "4689"}, "config": {"threshold": 11}}"#,
+        )
+        .unwrap();
+        let env = metadata.guest_env();
+        assert_eq!(env.vars["CHAIN_ID"], "4689");
+        assert_eq!(env.config.as_deref(), Some(r#"{"threshold":11}"#));
+
+        assert!(ProjectMetadata::from_bytes(br#"{"env": {"A=B": "1"}}"#).is_err());
+        assert!(ProjectMetadata::from_bytes(br#"{"groth16": true, "env": {"A": "1"}}"#).is_err());
+    }
+}
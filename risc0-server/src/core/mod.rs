@@ -1 +1,12 @@
+pub mod binding;
+pub mod bonsai;
+pub mod journal;
+pub mod metadata;
+pub mod output;
+pub mod owner;
 pub mod prover;
+pub mod receipt;
+pub mod retry;
+pub mod session;
+pub mod store;
+pub mod version;
@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Per-stream cap on captured guest output.
+pub const GUEST_OUTPUT_LIMIT: usize = 64 * 1024;
+
+/// Guest stdout and stderr captured during a task.
+///
+/// Each stream keeps at most `limit` bytes; anything past that is dropped and
+/// the stream is marked truncated.
+#[derive(Clone)]
+pub struct GuestOutput {
+    stdout: CappedBuffer,
+    stderr: CappedBuffer,
+}
+
+impl GuestOutput {
+    pub fn new(limit: usize) -> Self {
+        GuestOutput {
+            stdout: CappedBuffer::new(limit),
+            stderr: CappedBuffer::new(limit),
+        }
+    }
+
+    pub fn stdout_writer(&self) -> impl Write {
+        self.stdout.clone()
+    }
+
+    pub fn stderr_writer(&self) -> impl Write {
+        self.stderr.clone()
+    }
+
+    pub fn stdout(&self) -> Vec<u8> {
+        self.stdout.bytes()
+    }
+
+    pub fn stderr(&self) -> Vec<u8> {
+        self.stderr.bytes()
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.stdout.truncated() || self.stderr.truncated()
+    }
+
+    /// Logs the captured output line by line under the current span.
+    pub fn log(&self) {
+        for line in String::from_utf8_lossy(&self.stdout()).lines() {
+            tracing::info!(target: "guest", "{}", line);
+        }
+        for line in String::from_utf8_lossy(&self.stderr()).lines() {
+            tracing::warn!(target: "guest", "{}", line);
+        }
+        if self.truncated() {
+            tracing::warn!(target: "guest", "guest output truncated");
+        }
+    }
+
+    /// Drops everything captured so far, e.g. before retrying a task.
+    pub fn clear(&self) {
+        self.stdout.clear();
+        self.stderr.clear();
+    }
+}
+
+impl Default for GuestOutput {
+    fn default() -> Self {
+        GuestOutput::new(GUEST_OUTPUT_LIMIT)
+    }
+}
+
+#[derive(Default)]
+struct Buffer {
+    bytes: Vec<u8>,
+    truncated: bool,
+}
+
+#[derive(Clone)]
+struct CappedBuffer {
+    inner: Arc<Mutex<Buffer>>,
+    limit: usize,
+}
+
+impl CappedBuffer {
+    fn new(limit: usize) -> Self {
+        CappedBuffer {
+            inner: Arc::new(Mutex::new(Buffer::default())),
+            limit,
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        self.inner.lock().unwrap().bytes.clone()
+    }
+
+    fn truncated(&self) -> bool {
+        self.inner.lock().unwrap().truncated
+    }
+
+    fn clear(&self) {
+        *self.inner.lock().unwrap() = Buffer::default();
+    }
+}
+
+impl Write for CappedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.inner.lock().unwrap();
+        let room = self.limit.saturating_sub(inner.bytes.len());
+        if buf.len() > room {
+            inner.truncated = true;
+        }
+        let n = buf.len().min(room);
+        inner.bytes.extend_from_slice(&buf[..n]);
+        // Report everything as written so the guest never sees an I/O error.
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guest_output_cap() {
+        let output = GuestOutput::new(8);
+        let mut stdout = output.stdout_writer();
+        stdout.write_all(b"hello ").unwrap();
+        stdout.write_all(b"world").unwrap();
+        output.stderr_writer().write_all(b"oops").unwrap();
+
+        assert_eq!(output.stdout(), b"hello wo");
+        assert_eq!(output.stderr(), b"oops");
+        assert!(output.truncated());
+
+        output.clear();
+        assert!(output.stdout().is_empty());
+        assert!(!output.truncated());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use ethers::types::{Address, Signature};
+use ethers::utils::keccak256;
+use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// A key allowed to sign for a project.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum OwnerKey {
+    /// An Ethereum address, signing 65-byte secp256k1 EIP-191 signatures.
+    Secp256k1(Address),
+    /// An ed25519 public key, signing the 32-byte digest itself.
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+impl OwnerKey {
+    fn verify(&self, digest: &[u8; 32], signature: &[u8]) -> bool {
+        match self {
+            OwnerKey::Secp256k1(address) => Signature::try_from(signature)
+                .and_then(|sig| sig.recover(digest.to_vec()))
+                .is_ok_and(|signer| signer == *address),
+            OwnerKey::Ed25519(key) => ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|sig| key.verify_strict(digest, &sig).is_ok()),
+        }
+    }
+}
+
+/// Hex strings of 20 bytes are addresses and of 32 bytes ed25519 keys.
+impl TryFrom<String> for OwnerKey {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let bytes = hex::decode(s.trim_start_matches("0x"))
+            .map_err(|e| format!("invalid owner key {}: {}", s, e))?;
+        match bytes.len() {
+            20 => Ok(OwnerKey::Secp256k1(Address::from_slice(&bytes))),
+            32 => ed25519_dalek::VerifyingKey::try_from(bytes.as_slice())
+                .map(OwnerKey::Ed25519)
+                .map_err(|e| format!("invalid ed25519 key {}: {}", s, e)),
+            n => Err(format!(
+                "owner key {} must be a 20-byte address or 32-byte ed25519 key, got {} bytes",
+                s, n
+            )),
+        }
+    }
+}
+
+/// Owner keys allowed to register and delete each project.
+///
+/// When configured, every NewProject and DeleteProject must carry a signature
+/// by one of the project's owners; projects with no owners cannot be
+/// registered.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectOwners {
+    owners: HashMap<String, Vec<OwnerKey>>,
+}
+
+impl ProjectOwners {
+    pub fn new(owners: HashMap<String, Vec<OwnerKey>>) -> Self {
+        ProjectOwners { owners }
+    }
+
+    /// Reads a JSON object mapping project IDs to lists of owner keys.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read owners file {}", path.display()))?;
+        let owners = serde_json::from_slice(&bytes)
+            .with_context(|| format!("Failed to parse owners file {}", path.display()))?;
+        Ok(ProjectOwners::new(owners))
+    }
+
+    /// Checks `signature` signs [`upload_digest`] by an owner of `project_id`.
+    pub fn verify(
+        &self,
+        project_id: &str,
+        version: &str,
+        binary: &[u8],
+        metadata: &[u8],
+        signature: &[u8],
+    ) -> Result<()> {
+        let digest = upload_digest(project_id, version, binary, metadata);
+        self.verify_digest(project_id, &digest, signature)
+            .context("upload rejected")
+    }
+
+    /// Checks `signature` signs [`delete_digest`] by an owner of `project_id`.
+    pub fn verify_delete(&self, project_id: &str, version: &str, signature: &[u8]) -> Result<()> {
+        let digest = delete_digest(project_id, version);
+        self.verify_digest(project_id, &digest, signature)
+            .context("delete rejected")
+    }
+
+    fn verify_digest(&self, project_id: &str, digest: &[u8; 32], signature: &[u8]) -> Result<()> {
+        let Some(owners) = self.owners.get(project_id) else {
+            bail!("project {} has no registered owner", project_id);
+        };
+        if signature.is_empty() {
+            bail!("request is not signed");
+        }
+        if !owners.iter().any(|owner| owner.verify(digest, signature)) {
+            bail!("signature is not by an owner of project {}", project_id);
+        }
+        Ok(())
+    }
+}
+
+/// The 32 bytes an owner signs to register a project: keccak256 over the
+/// length-prefixed project ID and version followed by the SHA-256 of the
+/// uploaded binary and of the metadata.
+pub fn upload_digest(project_id: &str, version: &str, binary: &[u8], metadata: &[u8]) -> [u8; 32] {
+    let mut data = length_prefixed(&[project_id, version]);
+    data.extend_from_slice(&Sha256::digest(binary));
+    data.extend_from_slice(&Sha256::digest(metadata));
+    keccak256(data)
+}
+
+/// The 32 bytes an owner signs to delete a project version: keccak256 over
+/// the length-prefixed project ID, version and the word "delete".
+pub fn delete_digest(project_id: &str, version: &str) -> [u8; 32] {
+    keccak256(length_prefixed(&[project_id, version, "delete"]))
+}
+
+fn length_prefixed(fields: &[&str]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for field in fields {
+        data.extend_from_slice(&(field.len() as u64).to_le_bytes());
+        data.extend_from_slice(field.as_bytes());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use ethers::signers::{LocalWallet, Signer};
+
+    #[tokio::test]
+    async fn test_verify() {
+        let owner: LocalWallet = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse()
+            .unwrap();
+        let other: LocalWallet = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+            .parse()
+            .unwrap();
+        let owners = ProjectOwners::new(HashMap::from([(
+            "test1".to_string(),
+            vec![OwnerKey::Secp256k1(owner.address())],
+        )]));
+
+        let digest = upload_digest("test1", "1.0", b"binary", b"{}");
+        let signature = owner.sign_message(digest).await.unwrap().to_vec();
+        owners
+            .verify("test1", "1.0", b"binary", b"{}", &signature)
+            .unwrap();
+
+        // Any change to what was signed breaks the signature.
+        assert!(owners
+            .verify("test1", "1.1", b"binary", b"{}", &signature)
+            .is_err());
+        assert!(owners
+            .verify("test1", "1.0", b"binary2", b"{}", &signature)
+            .is_err());
+        assert!(owners
+            .verify(
+                "test1",
+                "1.0",
+                b"binary",
+                br#"{"groth16": true}"#,
+                &signature
+            )
+            .is_err());
+        assert!(owners
+            .verify("test2", "1.0", b"binary", b"{}", &signature)
+            .is_err());
+        assert!(owners
+            .verify("test1", "1.0", b"binary", b"{}", &[])
+            .is_err());
+        // An upload signature does not authorize a delete.
+        assert!(owners.verify_delete("test1", "1.0", &signature).is_err());
+
+        let signature = other.sign_message(digest).await.unwrap().to_vec();
+        assert!(owners
+            .verify("test1", "1.0", b"binary", b"{}", &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_ed25519() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let owners: HashMap<String, Vec<OwnerKey>> = serde_json::from_value(serde_json::json!({
+            "test1": [format!("0x{}", hex::encode(key.verifying_key().as_bytes()))],
+        }))
+        .unwrap();
+        let owners = ProjectOwners::new(owners);
+
+        let signature = key.sign(&delete_digest("test1", "1.0")).to_bytes();
+        owners.verify_delete("test1", "1.0", &signature).unwrap();
+        assert!(owners.verify_delete("test1", "1.1", &signature).is_err());
+
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        let signature = other.sign(&delete_digest("test1", "1.0")).to_bytes();
+        assert!(owners.verify_delete("test1", "1.0", &signature).is_err());
+    }
+
+    #[test]
+    fn test_parse_owner_keys() {
+        let key: OwnerKey =
+            serde_json::from_str(r#""0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266""#).unwrap();
+        assert!(matches!(key, OwnerKey::Secp256k1(_)));
+        assert!(serde_json::from_str::<OwnerKey>(r#""0x1234""#).is_err());
+    }
+}
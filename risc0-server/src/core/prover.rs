@@ -1,26 +1,52 @@
+use std::collections::BTreeMap;
 use std::env;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Arc;
 
-use anyhow::Result;
-use dotenvy::dotenv;
+use anyhow::{bail, Result};
 use dyn_clone::DynClone;
 use risc0_zkvm::{
-    default_prover, ExecutorEnv, Prover as risc0Prover, ProverOpts, Receipt, VerifierContext,
+    default_executor, default_prover, serde::to_vec, ExecutorEnv, ExitCode, Prover as risc0Prover,
+    ProverOpts, Receipt,
 };
+use tracing::{info, warn};
 
-const SESSION_LIMIT: u64 = 5_000_000;
+use super::bonsai::{BonsaiClient, BonsaiConfig, BonsaiError};
+use super::output::GuestOutput;
+use super::session::{SavedSession, SessionStore};
+
+/// Default cap on guest cycles per session.
+pub const SESSION_LIMIT: u64 = 5_000_000;
 
 pub trait Prover: DynClone + Send + Sync {
-    fn prove(&self, data: Vec<String>) -> Result<Receipt>;
+    /// Proves the guest over `data`, capturing what it prints into `output`.
+    fn prove(&self, data: Vec<String>, output: &GuestOutput) -> Result<Proof>;
 }
 
 dyn_clone::clone_trait_object!(Prover);
 
+pub struct Proof {
+    pub receipt: Receipt,
+    /// Total guest cycles, when the backend reports them.
+    pub cycles: Option<u64>,
+}
+
+/// Per-project guest environment: variables plus an optional config string
+/// written ahead of the task payloads.
+#[derive(Clone, Debug, Default)]
+pub struct GuestEnv {
+    pub vars: BTreeMap<String, String>,
+    pub config: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct LocalProver {
     prover: Arc<ProverWrapper>,
-    elf: Vec<u8>,
+    elf: Arc<[u8]>,
+    env: GuestEnv,
+    profile_path: Option<PathBuf>,
+    session_limit: u64,
 }
 
 struct ProverWrapper {
@@ -37,119 +63,323 @@ unsafe impl Send for ProverWrapper {}
 unsafe impl Sync for ProverWrapper {}
 
 impl LocalProver {
-    pub fn new(elf: &[u8]) -> Self {
+    pub fn new(elf: Arc<[u8]>) -> Self {
         env::set_var("RISC0_PROVER", "ipc");
         LocalProver {
             prover: Arc::new(ProverWrapper::new(default_prover())),
-            elf: elf.to_vec(),
+            elf,
+            env: GuestEnv::default(),
+            profile_path: None,
+            session_limit: SESSION_LIMIT,
+        }
+    }
+
+    /// Checks the r0vm binary used for IPC proving can be found. Dev mode
+    /// fakes proofs without it.
+    pub fn check_available() -> Result<()> {
+        let dev_mode = env::var("RISC0_DEV_MODE")
+            .is_ok_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        if dev_mode {
+            return Ok(());
+        }
+        if let Ok(path) = env::var("RISC0_SERVER_PATH") {
+            if !Path::new(&path).is_file() {
+                bail!("RISC0_SERVER_PATH {} does not exist", path);
+            }
+            return Ok(());
         }
+        let found = env::var_os("PATH")
+            .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join("r0vm").is_file()));
+        if !found {
+            bail!("r0vm not found in PATH");
+        }
+        Ok(())
+    }
+
+    pub fn with_env(mut self, env: GuestEnv) -> Self {
+        self.env = env;
+        self
+    }
+
+    pub fn with_session_limit(mut self, cycles: u64) -> Self {
+        self.session_limit = cycles;
+        self
+    }
+
+    /// Writes a gzipped pprof cycle profile of each run to `path`.
+    pub fn with_profiler(mut self, path: &Path) -> Self {
+        self.profile_path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Recompresses `receipt` as `opts` asks, e.g. composite to succinct.
+    pub fn compress(&self, receipt: &Receipt, opts: &ProverOpts) -> Result<Receipt> {
+        self.prover.inner.compress(opts, receipt)
+    }
+
+    /// Runs the guest without proving and returns the total cycle count.
+    pub fn execute(&self, data: Vec<String>, output: &GuestOutput) -> Result<u64> {
+        let env = self.executor_env(data, output)?;
+        let info = default_executor().execute(env, &self.elf)?;
+        check_exit_code(info.exit_code)?;
+        Ok(info.segments.iter().map(|s| s.cycles as u64).sum())
+    }
+
+    fn executor_env(
+        &self,
+        data: Vec<String>,
+        output: &GuestOutput,
+    ) -> Result<ExecutorEnv<'static>> {
+        let mut builder = ExecutorEnv::builder();
+        builder
+            .session_limit(Some(self.session_limit))
+            .stdout(output.stdout_writer())
+            .stderr(output.stderr_writer());
+        if let Some(path) = &self.profile_path {
+            builder.enable_profiler(path);
+        }
+        for (name, value) in &self.env.vars {
+            builder.env_var(name, value);
+        }
+        if let Some(config) = &self.env.config {
+            builder.write(config)?;
+        }
+        builder.write(&data)?.build()
     }
 }
 
-impl Prover for LocalProver {
-    fn prove(&self, data: Vec<String>) -> Result<Receipt> {
-        let env = ExecutorEnv::builder()
-            .session_limit(Some(SESSION_LIMIT))
-            .write(&data)?
-            .build()?;
+/// A guest that did not halt with status 0 has no result worth proving.
+fn check_exit_code(exit_code: ExitCode) -> Result<()> {
+    match exit_code {
+        ExitCode::Halted(0) => Ok(()),
+        ExitCode::SessionLimit => bail!("Session limit exceeded"),
+        code => bail!("Guest stopped with exit code {:?}", code),
+    }
+}
 
-        Ok(self.prover.inner.prove(env, &self.elf)?.receipt)
+impl Prover for LocalProver {
+    fn prove(&self, data: Vec<String>, output: &GuestOutput) -> Result<Proof> {
+        let env = self.executor_env(data, output)?;
+        let info = self.prover.inner.prove(env, &self.elf)?;
+        Ok(Proof {
+            receipt: info.receipt,
+            cycles: Some(info.stats.total_cycles),
+        })
     }
 }
 
 #[derive(Clone)]
 pub struct BonsaiProver {
-    elf: Vec<u8>,
-    prover: Arc<ProverWrapper>,
+    image_id: String,
+    elf: Arc<[u8]>,
+    client: BonsaiClient,
+    env: GuestEnv,
+    // Where this task's session is checkpointed, under the task's key.
+    sessions: Option<(SessionStore, String)>,
 }
 
 impl BonsaiProver {
-    pub fn new(elf: &[u8]) -> Self {
-        dotenv().ok();
+    /// `image_id` must be the ELF's image ID, as already checked at
+    /// registration; it names the image on Bonsai.
+    pub fn new(elf: Arc<[u8]>, image_id: String, config: &BonsaiConfig) -> Result<Self> {
+        Ok(BonsaiProver {
+            image_id,
+            elf,
+            client: BonsaiClient::new(config)?,
+            env: GuestEnv::default(),
+            sessions: None,
+        })
+    }
 
-        env::var("BONSAI_API_URL").expect("BONSAI_API_URL must be set");
-        env::var("BONSAI_API_KEY").expect("BONSAI_API_KEY must be set");
-        env::set_var("RISC0_PROVER", "bonsai");
-        BonsaiProver {
-            prover: Arc::new(ProverWrapper::new(default_prover())),
-            elf: elf.to_vec(),
-        }
+    pub fn with_env(mut self, env: GuestEnv) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Attributes the Bonsai requests of this prover to `project_id` in the
+    /// metrics.
+    pub fn with_project_id(mut self, project_id: &str) -> Self {
+        self.client = self.client.with_project_id(project_id);
+        self
+    }
+
+    /// Checkpoints the Bonsai session to `store` under `key`, and resumes one
+    /// found there instead of starting over.
+    pub fn with_session_store(mut self, store: SessionStore, key: String) -> Self {
+        self.sessions = Some((store, key));
+        self
+    }
+
+    /// Sends the ELF to Bonsai unless it already has it, returning the image ID
+    /// it is stored under. `prove` relies on this having been done once.
+    pub fn upload_image(&self) -> Result<String> {
+        self.client.upload_image(&self.image_id, &self.elf)?;
+        Ok(self.image_id.clone())
     }
 }
 
 impl Prover for BonsaiProver {
-    fn prove(&self, data: Vec<String>) -> Result<Receipt> {
-        let env = ExecutorEnv::builder()
-            .session_limit(Some(SESSION_LIMIT))
-            .write(&data)?
-            .build()?;
-
-        Ok(self
-            .prover
-            .inner
-            .prove_with_ctx(
-                // no bonsai -> Groth16Receipt   with bonsai -> Groth16
-                env,
-                &VerifierContext::default(),
-                &self.elf,
-                &ProverOpts::groth16(),
-            )?
-            .receipt)
+    // Bonsai does not hand guest output back, so `output` stays empty.
+    fn prove(&self, data: Vec<String>, _output: &GuestOutput) -> Result<Proof> {
+        if !self.env.vars.is_empty() {
+            bail!("Bonsai does not support guest environment variables");
+        }
+        let mut input = match &self.env.config {
+            Some(config) => to_vec(config)?,
+            None => vec![],
+        };
+        input.extend(to_vec(&data)?);
+        let input = bytemuck::cast_slice(&input).to_vec();
+        let receipt = match &self.sessions {
+            Some((store, key)) => {
+                let resume = store
+                    .load(key)
+                    .filter(|saved| saved.image_id == self.image_id)
+                    .map(|saved| saved.progress);
+                if let Some(progress) = &resume {
+                    info!("Resuming Bonsai session {}", progress.session);
+                }
+                let result = self.client.prove_groth16_resumable(
+                    &self.image_id,
+                    input,
+                    resume,
+                    |progress| {
+                        let saved = SavedSession {
+                            image_id: self.image_id.clone(),
+                            progress: progress.clone(),
+                        };
+                        if let Err(e) = store.save(key, &saved) {
+                            warn!("Failed to save Bonsai session: {:#}", e);
+                        }
+                    },
+                );
+                // A session we only stopped polling may still finish, so keep it
+                // for the retry; anything else is done with.
+                if !matches!(result, Err(BonsaiError::Timeout { .. })) {
+                    store.remove(key);
+                }
+                result?
+            }
+            None => self.client.prove_groth16(&self.image_id, input)?,
+        };
+        Ok(Proof {
+            receipt,
+            cycles: None,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
+    use crate::core::retry::{classify, ErrorClass};
+    use crate::tests::bonsai_mock::{groth16_receipt, MockBonsai};
 
     pub const HELLO_GUEST_ID: [u32; 8] = [
         1729087496, 1782151309, 3201877536, 1628959901, 2308880694, 3762575800, 943710734,
         1150869179,
     ];
 
-    fn create_dummy_elf() -> Vec<u8> {
-        include_bytes!("../tests/hello_guest").to_vec()
+    fn create_dummy_elf() -> Arc<[u8]> {
+        include_bytes!("../tests/hello_guest").as_slice().into()
+    }
+
+    fn hello_guest_image_id() -> String {
+        risc0_zkvm::sha::Digest::from(HELLO_GUEST_ID).to_string()
     }
 
     #[test]
     fn test_local_prover_creation() {
         let elf = create_dummy_elf();
-        let prover = LocalProver::new(&elf);
+        let prover = LocalProver::new(elf.clone());
         assert_eq!(prover.elf, elf);
     }
 
     #[test]
     fn test_local_prover_prove() {
         let elf = create_dummy_elf();
-        let prover = LocalProver::new(&elf);
-        let result = prover.prove(vec!["test1".to_string(), "test2".to_string()]);
-        let verify_result = result.unwrap().verify(HELLO_GUEST_ID);
+        let prover = LocalProver::new(elf.clone());
+        let output = GuestOutput::default();
+        let proof = prover
+            .prove(vec!["test1".to_string(), "test2".to_string()], &output)
+            .unwrap();
+        let verify_result = proof.receipt.verify(HELLO_GUEST_ID);
         assert!(verify_result.is_ok(), "Error: {:?}", verify_result.err());
+        assert!(proof.cycles.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_local_prover_execute() {
+        let prover = LocalProver::new(create_dummy_elf());
+        let cycles = prover
+            .execute(
+                vec!["14".to_string(), "3,34".to_string()],
+                &GuestOutput::default(),
+            )
+            .unwrap();
+        assert!(cycles > 0);
+    }
+
+    #[test]
+    fn test_check_exit_code() {
+        assert!(check_exit_code(ExitCode::Halted(0)).is_ok());
+        for code in [
+            ExitCode::Halted(1),
+            ExitCode::Paused(0),
+            ExitCode::SessionLimit,
+        ] {
+            let err = check_exit_code(code).unwrap_err();
+            assert_eq!(classify(&err), ErrorClass::GuestFault, "{:?}", code);
+        }
     }
 
     #[test]
     fn test_bonsai_prover_creation() {
-        // Set dummy environment variables
-        env::set_var("BONSAI_API_URL", "http://dummy-url.com");
-        env::set_var("BONSAI_API_KEY", "dummy-key");
+        let config = BonsaiConfig::new("http://dummy-url.com", "dummy-key");
 
         let elf = create_dummy_elf();
-        let prover = BonsaiProver::new(&elf);
+        let prover = BonsaiProver::new(elf.clone(), hello_guest_image_id(), &config).unwrap();
         assert_eq!(prover.elf, elf);
     }
 
-    // #[test]
-    // fn test_bonsai_prover_prove() {
-    //     // Set dummy environment variables
-    //     env::set_var("BONSAI_API_URL", "https://api.bonsai.xyz");
-    //     env::set_var("BONSAI_API_KEY", "");
+    #[test]
+    fn test_bonsai_prover_rejects_env_vars() {
+        let config = BonsaiConfig::new("http://dummy-url.com", "dummy-key");
+        let env = GuestEnv {
+            vars: BTreeMap::from([("CHAIN_ID".to_string(), "4689".to_string())]),
+            config: None,
+        };
+
+        let prover = BonsaiProver::new(create_dummy_elf(), hello_guest_image_id(), &config)
+            .unwrap()
+            .with_env(env);
+        assert!(prover
+            .prove(vec!["test".to_string()], &GuestOutput::default())
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bonsai_prover_prove() {
+        let fixture = groth16_receipt();
+        let mock = MockBonsai::start(fixture.clone()).await;
+        let config = mock.config();
 
-    //     let elf = create_dummy_elf();
-    //     let prover = BonsaiProver::new(&elf);
-    //     let result = prover.prove(vec!["test".to_string(), "test2".to_string()]);
+        let proof = tokio::task::spawn_blocking(move || {
+            let elf = create_dummy_elf();
+            let prover = BonsaiProver::new(elf.clone(), hello_guest_image_id(), &config).unwrap();
+            prover.upload_image().unwrap();
+            prover.prove(
+                vec!["test".to_string(), "test2".to_string()],
+                &GuestOutput::default(),
+            )
+        })
+        .await
+        .unwrap()
+        .unwrap();
 
-    //     let ans: u32 = result.unwrap().journal.decode().unwrap();
-    //     println!("ans: {}", ans);
-    // }
+        assert_eq!(proof.receipt.journal, fixture.journal);
+        assert_eq!(proof.cycles, None);
+        assert_eq!(mock.image_uploads(), 1);
+        assert_eq!(mock.sessions(), 1);
+    }
 }
@@ -0,0 +1,222 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use ethers::abi::{decode, encode, ParamType, Token};
+use risc0_ethereum_contracts::groth16;
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    Groth16Receipt, Groth16ReceiptVerifierParameters, InnerReceipt, MaybePruned, ProverOpts,
+    Receipt, ReceiptClaim, VerifierContext,
+};
+use thiserror::Error;
+
+const SELECTOR_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReceiptKind {
+    Composite,
+    Succinct,
+    Groth16,
+    Fake,
+}
+
+impl ReceiptKind {
+    pub fn of(receipt: &Receipt) -> Self {
+        match receipt.inner {
+            InnerReceipt::Composite(_) => ReceiptKind::Composite,
+            InnerReceipt::Succinct(_) => ReceiptKind::Succinct,
+            InnerReceipt::Groth16(_) => ReceiptKind::Groth16,
+            _ => ReceiptKind::Fake,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReceiptKind::Composite => "Composite",
+            ReceiptKind::Succinct => "Succinct",
+            ReceiptKind::Groth16 => "Groth16",
+            ReceiptKind::Fake => "Fake",
+        }
+    }
+}
+
+impl FromStr for ReceiptKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "Composite" => Ok(ReceiptKind::Composite),
+            "Succinct" => Ok(ReceiptKind::Succinct),
+            "Groth16" => Ok(ReceiptKind::Groth16),
+            _ => Err(anyhow!("unsupported receipt type: {}", s)),
+        }
+    }
+}
+
+/// Returns the prover options that upgrade a receipt of kind `from` to kind `to`.
+pub fn conversion_opts(from: ReceiptKind, to: ReceiptKind) -> Result<ProverOpts> {
+    if from == ReceiptKind::Fake {
+        bail!("fake receipts cannot be converted");
+    }
+    if to <= from {
+        bail!(
+            "cannot convert {} receipt to {}",
+            from.as_str(),
+            to.as_str()
+        );
+    }
+    match to {
+        ReceiptKind::Succinct => Ok(ProverOpts::succinct()),
+        ReceiptKind::Groth16 => Ok(ProverOpts::groth16()),
+        _ => bail!("unsupported conversion target: {}", to.as_str()),
+    }
+}
+
+/// Encodes a receipt the way it is returned to clients: Groth16 receipts are
+/// verified and ABI-encoded as (seal, journal), everything else is serde_json.
+/// A Groth16 receipt that fails verification is reported as a
+/// `VerificationError`.
+pub fn encode_receipt(receipt: &Receipt, image_id: Digest) -> Result<Vec<u8>> {
+    if let InnerReceipt::Groth16(groth16_receipt) = &receipt.inner {
+        verify_groth16(receipt, image_id)?;
+
+        let seal = groth16::encode(groth16_receipt.seal.clone())?;
+        let journal = receipt.journal.bytes.clone();
+
+        let tokens = vec![Token::Bytes(seal), Token::Bytes(journal)];
+        return Ok(encode(&tokens));
+    }
+
+    Ok(serde_json::to_vec(receipt)?)
+}
+
+/// A receipt that does not verify, as opposed to one that could not be
+/// processed at all.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct VerificationError(String);
+
+/// Checks a Groth16 receipt against risc0's Groth16 verifier parameters, so a
+/// bad seal is caught here instead of reverting on-chain.
+pub fn verify_groth16(receipt: &Receipt, image_id: Digest) -> Result<(), VerificationError> {
+    let InnerReceipt::Groth16(groth16_receipt) = &receipt.inner else {
+        return Err(VerificationError(format!(
+            "expected a Groth16 receipt, got {}",
+            ReceiptKind::of(receipt).as_str()
+        )));
+    };
+
+    let expected = Groth16ReceiptVerifierParameters::default().digest();
+    if groth16_receipt.verifier_parameters != expected {
+        return Err(VerificationError(format!(
+            "Groth16 verifier parameters mismatch: expected {}, got {}",
+            expected, groth16_receipt.verifier_parameters
+        )));
+    }
+
+    receipt
+        .verify_with_context(&VerifierContext::default(), image_id)
+        .map_err(|e| VerificationError(format!("Groth16 receipt verification failed: {}", e)))
+}
+
+/// Decodes a receipt in any of the encodings produced by `encode_receipt`.
+///
+/// ABI-encoded Groth16 results only carry the seal and journal, so the claim
+/// is rebuilt from `image_id` and the journal.
+pub fn decode_receipt(bytes: &[u8], image_id: Digest) -> Result<Receipt> {
+    if let Ok(receipt) = serde_json::from_slice::<Receipt>(bytes) {
+        return Ok(receipt);
+    }
+
+    let tokens = decode(&[ParamType::Bytes, ParamType::Bytes], bytes)
+        .map_err(|_| anyhow!("receipt is neither serde_json nor ABI-encoded (seal, journal)"))?;
+    let (seal, journal) = match tokens.as_slice() {
+        [Token::Bytes(seal), Token::Bytes(journal)] => (seal.clone(), journal.clone()),
+        _ => bail!("unexpected ABI tokens in receipt"),
+    };
+    if seal.len() < SELECTOR_LEN {
+        bail!("Groth16 seal is too short");
+    }
+
+    let verifier_parameters = Groth16ReceiptVerifierParameters::default().digest();
+    if seal[..SELECTOR_LEN] != verifier_parameters.as_bytes()[..SELECTOR_LEN] {
+        bail!(
+            "unknown Groth16 seal selector 0x{}",
+            hex::encode(&seal[..SELECTOR_LEN])
+        );
+    }
+
+    let claim = ReceiptClaim::ok(image_id, journal.clone());
+    let inner = InnerReceipt::Groth16(Groth16Receipt::new(
+        seal[SELECTOR_LEN..].to_vec(),
+        MaybePruned::Value(claim),
+        verifier_parameters,
+    ));
+
+    Ok(Receipt::new(inner, journal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_kind_from_str() {
+        assert_eq!(
+            ReceiptKind::from_str("Succinct").unwrap(),
+            ReceiptKind::Succinct
+        );
+        assert_eq!(
+            ReceiptKind::from_str("Groth16").unwrap(),
+            ReceiptKind::Groth16
+        );
+        assert!(ReceiptKind::from_str("Snark").is_err());
+    }
+
+    #[test]
+    fn test_decode_receipt_invalid() {
+        let image_id = Digest::default();
+        assert!(decode_receipt(b"not a receipt", image_id).is_err());
+
+        let tokens = vec![Token::Bytes(vec![0, 1, 2, 3, 4]), Token::Bytes(vec![])];
+        assert!(decode_receipt(&encode(&tokens), image_id).is_err());
+    }
+
+    #[test]
+    fn test_verify_groth16_rejects_bad_receipts() {
+        let receipt: Receipt =
+            serde_json::from_str(include_str!("../tests/groth16_receipt.json")).unwrap();
+        let image_id = receipt.claim().unwrap().as_value().unwrap().pre.digest();
+
+        // The fixture itself passes, so the failures below come from tampering.
+        verify_groth16(&receipt, image_id).unwrap();
+        assert!(!encode_receipt(&receipt, image_id).unwrap().is_empty());
+
+        let mut tampered = receipt.clone();
+        if let InnerReceipt::Groth16(inner) = &mut tampered.inner {
+            inner.seal[0] ^= 1;
+        }
+        assert!(verify_groth16(&tampered, image_id).is_err());
+        assert!(encode_receipt(&tampered, image_id)
+            .unwrap_err()
+            .is::<VerificationError>());
+
+        let mut tampered = receipt.clone();
+        if let InnerReceipt::Groth16(inner) = &mut tampered.inner {
+            inner.verifier_parameters = Digest::default();
+        }
+        assert!(verify_groth16(&tampered, image_id).is_err());
+
+        assert!(verify_groth16(&receipt, Digest::default()).is_err());
+    }
+
+    #[test]
+    fn test_conversion_opts() {
+        assert!(conversion_opts(ReceiptKind::Composite, ReceiptKind::Succinct).is_ok());
+        assert!(conversion_opts(ReceiptKind::Composite, ReceiptKind::Groth16).is_ok());
+        assert!(conversion_opts(ReceiptKind::Succinct, ReceiptKind::Groth16).is_ok());
+        assert!(conversion_opts(ReceiptKind::Succinct, ReceiptKind::Composite).is_err());
+        assert!(conversion_opts(ReceiptKind::Groth16, ReceiptKind::Groth16).is_err());
+        assert!(conversion_opts(ReceiptKind::Fake, ReceiptKind::Succinct).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+use std::io::ErrorKind;
+
+use anyhow::Result;
+use bonsai_sdk::SdkErr;
+
+use super::bonsai::BonsaiError;
+
+const GUEST_FAULT_MARKERS: [&str; 3] = ["guest panicked", "session limit exceeded", "exit code"];
+// Bonsai session states that say nothing about the guest: the session was cut
+// short, so running it again can succeed.
+const RETRYABLE_SESSION_STATUSES: [&str; 2] = ["TIMED_OUT", "ABORTED"];
+// bonsai-sdk keeps only the body of a failed HTTP call, not its status, so
+// server errors are recognised by what Bonsai and gateways put there.
+const SERVER_ERROR_MARKERS: [&str; 5] = [
+    "internal server error",
+    "bad gateway",
+    "service unavailable",
+    "gateway timeout",
+    "upstream",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Infrastructure hiccups: worth retrying.
+    Transient,
+    /// The guest itself failed; retrying gives the same result.
+    GuestFault,
+    /// Anything else that will not go away on retry.
+    Permanent,
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ErrorClass::Transient => "Transient",
+            ErrorClass::GuestFault => "GuestFault",
+            ErrorClass::Permanent => "Permanent",
+        };
+        f.write_str(s)
+    }
+}
+
+pub fn classify(e: &anyhow::Error) -> ErrorClass {
+    if let Some(err) = e.downcast_ref::<BonsaiError>() {
+        return match err {
+            BonsaiError::Request(err) => classify_request(err),
+            BonsaiError::SessionFailed { message, .. } if is_guest_fault(message) => {
+                ErrorClass::GuestFault
+            }
+            BonsaiError::SessionFailed { status, .. }
+                if RETRYABLE_SESSION_STATUSES.contains(&status.as_str()) =>
+            {
+                ErrorClass::Transient
+            }
+            _ => ErrorClass::Permanent,
+        };
+    }
+
+    if is_guest_fault(&format!("{:#}", e)) {
+        return ErrorClass::GuestFault;
+    }
+    // Only connection trouble is worth retrying; a missing or unreadable local
+    // file stays that way, and so does an error that carries no I/O cause.
+    match e
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .map(std::io::Error::kind)
+    {
+        Some(
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe
+            | ErrorKind::TimedOut
+            | ErrorKind::Interrupted
+            | ErrorKind::UnexpectedEof,
+        ) => ErrorClass::Transient,
+        _ => ErrorClass::Permanent,
+    }
+}
+
+/// Retries Bonsai calls that failed to connect, timed out or got a 5xx; 4xx
+/// responses (bad key, unknown image) and local errors fail immediately.
+fn classify_request(err: &SdkErr) -> ErrorClass {
+    match err {
+        SdkErr::HttpErr(e)
+            if e.is_connect()
+                || e.is_timeout()
+                || e.status().is_some_and(|s| s.is_server_error()) =>
+        {
+            ErrorClass::Transient
+        }
+        SdkErr::InternalServerErr(body) if is_server_error(body) => ErrorClass::Transient,
+        _ => ErrorClass::Permanent,
+    }
+}
+
+fn is_server_error(body: &str) -> bool {
+    let lower = body.to_lowercase();
+    SERVER_ERROR_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+}
+
+fn is_guest_fault(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    GUEST_FAULT_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retrying after the given (1-based) failed attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+}
+
+/// A failed attempt, as reported back with the task result.
+#[derive(Debug, Clone)]
+pub struct Attempt {
+    pub attempt: u32,
+    pub class: ErrorClass,
+    pub error: String,
+    pub backoff: Duration,
+}
+
+/// Context on the error of an operation that ran out of retries, carrying the
+/// attempts that failed before the last one.
+#[derive(Debug)]
+pub struct GaveUp {
+    pub attempts: Vec<Attempt>,
+}
+
+impl fmt::Display for GaveUp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gave up after {} attempt(s)", self.attempts.len() + 1)
+    }
+}
+
+/// Runs `f` until it succeeds, fails with a non-transient error or the policy
+/// runs out of attempts. Sleeps between attempts, so call it from a blocking
+/// thread.
+pub fn retry<T>(
+    policy: &RetryPolicy,
+    mut f: impl FnMut() -> Result<T>,
+) -> (Result<T>, Vec<Attempt>) {
+    let mut attempts = Vec::new();
+    let mut attempt = 1;
+    loop {
+        let e = match f() {
+            Ok(v) => return (Ok(v), attempts),
+            Err(e) => e,
+        };
+
+        let class = classify(&e);
+        if class != ErrorClass::Transient || attempt >= policy.max_attempts {
+            return (Err(e), attempts);
+        }
+
+        let backoff = policy.backoff(attempt);
+        tracing::warn!(
+            "Attempt {} failed({}), retrying in {:?}: {:#}",
+            attempt,
+            class,
+            backoff,
+            e
+        );
+        attempts.push(Attempt {
+            attempt,
+            class,
+            error: format!("{:#}", e),
+            backoff,
+        });
+        thread::sleep(backoff);
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            multiplier: 2.0,
+        }
+    }
+
+    fn connection_reset() -> anyhow::Error {
+        anyhow!(std::io::Error::new(
+            ErrorKind::ConnectionReset,
+            "r0vm ipc closed"
+        ))
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(&anyhow!("Guest panicked: invalid input")),
+            ErrorClass::GuestFault
+        );
+        assert_eq!(
+            classify(&anyhow!("Session limit exceeded")),
+            ErrorClass::GuestFault
+        );
+        assert_eq!(
+            classify(&anyhow!(std::io::Error::new(
+                ErrorKind::ConnectionReset,
+                "r0vm ipc closed"
+            ))),
+            ErrorClass::Transient
+        );
+        assert_eq!(
+            classify(&anyhow!(std::io::Error::new(
+                ErrorKind::NotFound,
+                "failed to spawn r0vm"
+            ))),
+            ErrorClass::Permanent
+        );
+        assert_eq!(
+            classify(
+                &anyhow!(std::io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    "guest.elf"
+                ))
+                .context("Failed to read ELF")
+            ),
+            ErrorClass::Permanent
+        );
+        assert_eq!(
+            classify(&anyhow!(BonsaiError::Request(SdkErr::InternalServerErr(
+                "503 Service Unavailable".to_string()
+            )))),
+            ErrorClass::Transient
+        );
+        assert_eq!(
+            classify(&anyhow!(BonsaiError::Request(SdkErr::InternalServerErr(
+                "invalid api key".to_string()
+            )))),
+            ErrorClass::Permanent
+        );
+        assert_eq!(
+            classify(&anyhow!(BonsaiError::Request(SdkErr::FileNotFound(
+                std::io::Error::new(ErrorKind::NotFound, "input.bin")
+            )))),
+            ErrorClass::Permanent
+        );
+        assert_eq!(
+            classify(&anyhow!(BonsaiError::QuotaExceeded("budget".to_string()))),
+            ErrorClass::Permanent
+        );
+        assert_eq!(
+            classify(&anyhow!(BonsaiError::SessionFailed {
+                kind: "session",
+                id: "1".to_string(),
+                status: "FAILED".to_string(),
+                message: "Guest panicked".to_string(),
+            })),
+            ErrorClass::GuestFault
+        );
+        for status in ["TIMED_OUT", "ABORTED", "FAILED"] {
+            let class = classify(&anyhow!(BonsaiError::SessionFailed {
+                kind: "session",
+                id: "1".to_string(),
+                status: status.to_string(),
+                message: "".to_string(),
+            }));
+            let expected = if status == "FAILED" {
+                ErrorClass::Permanent
+            } else {
+                ErrorClass::Transient
+            };
+            assert_eq!(class, expected, "{}", status);
+        }
+        assert_eq!(classify(&anyhow!("bad image")), ErrorClass::Permanent);
+        // Words that merely contain a transient-sounding one are not I/O errors.
+        assert_eq!(
+            classify(&anyhow!("unknown recipient principal")),
+            ErrorClass::Permanent
+        );
+        assert_eq!(
+            classify(&anyhow!("ipc connection reset")),
+            ErrorClass::Permanent
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(10), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_retry_transient_then_success() {
+        let mut calls = 0;
+        let (result, attempts) = retry(&fast_policy(), || {
+            calls += 1;
+            if calls < 3 {
+                Err(connection_reset())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].class, ErrorClass::Transient);
+    }
+
+    #[test]
+    fn test_retry_gives_up() {
+        let mut calls = 0;
+        let (result, attempts) = retry(&fast_policy(), || -> Result<()> {
+            calls += 1;
+            Err(connection_reset())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+        assert_eq!(attempts.len(), 2);
+    }
+
+    #[test]
+    fn test_retry_stops_on_guest_fault() {
+        let mut calls = 0;
+        let (result, attempts) = retry(&fast_policy(), || -> Result<()> {
+            calls += 1;
+            Err(anyhow!("Guest panicked"))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+        assert!(attempts.is_empty());
+    }
+}
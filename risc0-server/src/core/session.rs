@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_derive::{Deserialize, Serialize};
+
+/// How far a Bonsai proof got: the session, then the SNARK conversion once
+/// the session has succeeded.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BonsaiProgress {
+    pub session: String,
+    pub snark: Option<String>,
+}
+
+/// A Bonsai proof left running when the server stopped.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SavedSession {
+    pub image_id: String,
+    pub progress: BonsaiProgress,
+}
+
+/// Directory of in-progress Bonsai proofs, one JSON file per task, so a task
+/// retried after a restart picks up its session instead of proving again.
+#[derive(Clone, Debug)]
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create session dir {}", dir.display()))?;
+        Ok(SessionStore {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Keys are hex task digests, so they are safe as file names.
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub fn load(&self, key: &str) -> Option<SavedSession> {
+        let bytes = fs::read(self.path(key)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Written to a temporary file first so a crash never leaves half a record.
+    pub fn save(&self, key: &str, session: &SavedSession) -> Result<()> {
+        let tmp = self.dir.join(format!("{}.json.tmp", key));
+        fs::write(&tmp, serde_json::to_vec(session)?)?;
+        fs::rename(&tmp, self.path(key))?;
+        Ok(())
+    }
+
+    pub fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+
+    /// Number of saved sessions.
+    pub fn len(&self) -> usize {
+        fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::open(&dir.path().join("sessions")).unwrap();
+        let session = SavedSession {
+            image_id: "image".to_string(),
+            progress: BonsaiProgress {
+                session: "session-1".to_string(),
+                snark: None,
+            },
+        };
+
+        assert_eq!(store.load("task"), None);
+        store.save("task", &session).unwrap();
+        assert_eq!(store.load("task"), Some(session.clone()));
+        assert_eq!(store.len(), 1);
+
+        // Reopening sees what an earlier process saved.
+        let reopened = SessionStore::open(&dir.path().join("sessions")).unwrap();
+        assert_eq!(reopened.load("task"), Some(session));
+
+        store.remove("task");
+        assert_eq!(store.load("task"), None);
+        assert!(store.is_empty());
+    }
+}
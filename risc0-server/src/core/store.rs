@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use risc0_zkvm::sha::Digest;
+use tracing::warn;
+
+use crate::metrics::IMAGE_CACHE;
+
+/// Content store that hands out one shared copy of each ELF per image ID.
+///
+/// Entries are weak, so an image is freed once the last project using it is
+/// dropped.
+#[derive(Default)]
+pub struct ImageStore {
+    images: Mutex<HashMap<Digest, Weak<[u8]>>>,
+}
+
+impl ImageStore {
+    pub fn new() -> Self {
+        ImageStore::default()
+    }
+
+    /// Returns the stored copy of `elf` if one is already held for `image_id`,
+    /// otherwise stores and returns `elf`. `image_id` must be the ELF's own
+    /// image ID; `NewProject` checks it before interning. The lookup is
+    /// counted under `project_id`.
+    pub fn intern(&self, project_id: &str, image_id: Digest, elf: Vec<u8>) -> Arc<[u8]> {
+        let mut images = self.images.lock().unwrap();
+        images.retain(|_, image| image.strong_count() > 0);
+
+        if let Some(existing) = images.get(&image_id).and_then(Weak::upgrade) {
+            if *existing == *elf {
+                IMAGE_CACHE.with_label_values(&[project_id, "hit"]).inc();
+                return existing;
+            }
+            // Only reachable by callers that skipped the image ID check; keep
+            // the copy unshared rather than serve the wrong ELF.
+            warn!(
+                "ELF for image {} differs from the stored one, not sharing",
+                image_id
+            );
+            IMAGE_CACHE.with_label_values(&[project_id, "miss"]).inc();
+            return elf.into();
+        }
+
+        IMAGE_CACHE.with_label_values(&[project_id, "miss"]).inc();
+        let elf: Arc<[u8]> = elf.into();
+        images.insert(image_id, Arc::downgrade(&elf));
+        elf
+    }
+
+    /// Number of distinct images currently alive.
+    pub fn len(&self) -> usize {
+        self.images
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|image| image.strong_count() > 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_shares_images() {
+        let store = ImageStore::new();
+        let id = Digest::from([1u32; 8]);
+
+        let a = store.intern("p", id, vec![1, 2, 3]);
+        let b = store.intern("p", id, vec![1, 2, 3]);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(store.len(), 1);
+
+        // Same ID, different bytes: served, but not shared.
+        let c = store.intern("p", id, vec![4, 5, 6]);
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(&*c, &[4, 5, 6]);
+
+        let d = store.intern("p", Digest::from([2u32; 8]), vec![1, 2, 3]);
+        assert!(!Arc::ptr_eq(&a, &d));
+        assert_eq!(store.len(), 2);
+
+        drop((a, b, d));
+        assert!(store.is_empty());
+    }
+}
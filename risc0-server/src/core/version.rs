@@ -0,0 +1,127 @@
+use std::cmp::Ordering;
+
+use semver::{Version, VersionReq};
+
+pub const LATEST: &str = "latest";
+
+/// Characters a semver range starts with. Anything else is an exact version,
+/// so a bare "1.0" never widens to `^1.0`.
+const RANGE_OPERATORS: [char; 6] = ['^', '~', '<', '>', '=', '*'];
+
+/// Parses a project version as semver, accepting the short forms projects
+/// already use ("1.0", "v2").
+pub fn parse(version: &str) -> Option<Version> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    if let Ok(v) = Version::parse(version) {
+        return Some(v);
+    }
+    // Pad only the core part, before any pre-release or build suffix.
+    let split = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, suffix) = version.split_at(split);
+    let padded = match core.split('.').count() {
+        1 => format!("{}.0.0{}", core, suffix),
+        2 => format!("{}.0{}", core, suffix),
+        _ => return None,
+    };
+    Version::parse(&padded).ok()
+}
+
+/// Orders versions by semver; versions that are not semver sort below all
+/// that are, and by name among themselves.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (parse(a), parse(b)) {
+        (Some(x), Some(y)) => x.cmp(&y).then_with(|| a.cmp(b)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// Picks the registered version a request for `spec` targets: an exact
+/// match, `latest`, or the highest version matching a semver range. Only
+/// specs starting with a range operator are treated as ranges.
+///
+/// `latest` skips pre-releases unless there is no other version.
+pub fn resolve<'a>(spec: &str, versions: &[&'a str]) -> Option<&'a str> {
+    if let Some(exact) = versions.iter().find(|v| **v == spec) {
+        return Some(*exact);
+    }
+    let versions = versions.iter().copied();
+
+    if spec == LATEST {
+        let stable = highest(
+            versions
+                .clone()
+                .filter(|v| parse(v).is_some_and(|v| v.pre.is_empty())),
+        );
+        return stable.or_else(|| highest(versions));
+    }
+
+    if !spec.starts_with(RANGE_OPERATORS) {
+        return None;
+    }
+    let req = VersionReq::parse(spec).ok()?;
+    highest(versions.filter(|v| parse(v).is_some_and(|v| req.matches(&v))))
+}
+
+fn highest<'a>(versions: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    versions.max_by(|a, b| compare(a, b))
+}
+
+/// Returns the versions to drop so only the newest `keep` remain.
+pub fn expired<'a>(versions: &[&'a str], keep: usize) -> Vec<&'a str> {
+    let mut sorted = versions.to_vec();
+    sorted.sort_by(|a, b| compare(a, b));
+    let excess = sorted.len().saturating_sub(keep);
+    sorted.truncate(excess);
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("1.0"), Some(Version::new(1, 0, 0)));
+        assert_eq!(parse("v2"), Some(Version::new(2, 0, 0)));
+        assert_eq!(parse("1.2.3"), Some(Version::new(1, 2, 3)));
+        assert_eq!(parse("1.2-rc.1").unwrap().pre.as_str(), "rc.1");
+        assert_eq!(parse("main"), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let versions = ["0.9", "1.0", "1.2.0", "1.10.0", "2.0.0-rc.1", "dev"];
+
+        assert_eq!(resolve("dev", &versions), Some("dev"));
+        assert_eq!(resolve("1.0", &versions), Some("1.0"));
+        assert_eq!(resolve(LATEST, &versions), Some("1.10.0"));
+        assert_eq!(resolve("^1", &versions), Some("1.10.0"));
+        assert_eq!(resolve("~1.2", &versions), Some("1.2.0"));
+        assert_eq!(resolve("<1", &versions), Some("0.9"));
+        assert_eq!(resolve(">=2.0.0-rc.0", &versions), Some("2.0.0-rc.1"));
+        assert_eq!(resolve("^3", &versions), None);
+        assert_eq!(resolve("not a range", &versions), None);
+        assert_eq!(resolve("*", &versions), Some("1.10.0"));
+        assert_eq!(resolve("=1.2", &versions), Some("1.2.0"));
+
+        // A missing exact version does not fall back to a compatible one.
+        let pruned = ["1.10.0", "1.2.0"];
+        assert_eq!(resolve("1.0", &pruned), None);
+        assert_eq!(resolve("1", &pruned), None);
+        assert_eq!(resolve("^1.0", &pruned), Some("1.10.0"));
+
+        assert_eq!(resolve(LATEST, &["dev", "2.0.0-rc.1"]), Some("2.0.0-rc.1"));
+        assert_eq!(resolve(LATEST, &["a", "b"]), Some("b"));
+        assert_eq!(resolve(LATEST, &[]), None);
+    }
+
+    #[test]
+    fn test_expired() {
+        let versions = ["1.10.0", "dev", "1.2.0", "1.0"];
+        assert_eq!(expired(&versions, 2), vec!["dev", "1.0"]);
+        assert!(expired(&versions, 4).is_empty());
+        assert_eq!(expired(&versions, 0).len(), 4);
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use thiserror::Error;
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+
+use crate::core::bonsai::BonsaiError;
+use crate::core::output::GuestOutput;
+use crate::core::receipt::VerificationError;
+use crate::core::retry::{classify, Attempt, ErrorClass};
+
+const ERROR_DOMAIN: &str = "risc0-server";
+const UNAVAILABLE_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Every failure a handler can report, each with its own gRPC code and
+/// `google.rpc.ErrorInfo` reason so clients can branch without parsing messages.
+#[derive(Debug, Error)]
+pub enum ServerError {
+    #[error("invalid {field}: {reason}")]
+    BadInput { field: &'static str, reason: String },
+    #[error("project {project_id} version {version} not found")]
+    UnknownProject { project_id: String, version: String },
+    #[error("project {project_id} version {version} already exists")]
+    ProjectExists { project_id: String, version: String },
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("{0} is not configured")]
+    NotConfigured(&'static str),
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+    #[error("guest panicked: {0}")]
+    GuestPanic(String),
+    #[error("cycle limit exceeded: {0}")]
+    CycleLimitExceeded(String),
+    #[error("prover quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[error("prover timed out: {0}")]
+    ProverTimeout(String),
+    #[error("prover unavailable: {0}")]
+    ProverUnavailable(String),
+    #[error("verification failed: {0}")]
+    VerificationFailed(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ServerError {
+    pub fn bad_input(field: &'static str, reason: impl ToString) -> Self {
+        ServerError::BadInput {
+            field,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn internal(context: &str, e: impl std::fmt::Display) -> Self {
+        ServerError::Internal(format!("{}: {}", context, e))
+    }
+
+    /// Sorts a prover failure into guest, quota, availability or internal
+    /// errors.
+    pub fn from_prove_error(e: anyhow::Error) -> Self {
+        let message = format!("{:#}", e);
+        match e.downcast_ref::<BonsaiError>() {
+            Some(BonsaiError::QuotaExceeded(_)) => return ServerError::QuotaExceeded(message),
+            Some(BonsaiError::Timeout { .. }) => return ServerError::ProverTimeout(message),
+            _ => {}
+        }
+        match classify(&e) {
+            ErrorClass::GuestFault if message.to_lowercase().contains("session limit exceeded") => {
+                ServerError::CycleLimitExceeded(message)
+            }
+            ErrorClass::GuestFault => ServerError::GuestPanic(message),
+            ErrorClass::Transient => ServerError::ProverUnavailable(message),
+            ErrorClass::Permanent => ServerError::Internal(message),
+        }
+    }
+
+    /// A receipt that fails verification while being encoded is reported as
+    /// such; anything else is an internal error.
+    pub fn from_encode_error(e: anyhow::Error) -> Self {
+        if e.is::<VerificationError>() {
+            return ServerError::VerificationFailed(format!("{:#}", e));
+        }
+        ServerError::internal("Failed to encode receipt", format!("{:#}", e))
+    }
+
+    pub fn code(&self) -> Code {
+        match self {
+            ServerError::BadInput { .. } => Code::InvalidArgument,
+            ServerError::UnknownProject { .. } => Code::NotFound,
+            ServerError::ProjectExists { .. } => Code::AlreadyExists,
+            ServerError::PermissionDenied(_) => Code::PermissionDenied,
+            ServerError::NotConfigured(_) => Code::FailedPrecondition,
+            ServerError::Unsupported(_) => Code::FailedPrecondition,
+            ServerError::GuestPanic(_) => Code::Aborted,
+            ServerError::CycleLimitExceeded(_) => Code::ResourceExhausted,
+            ServerError::QuotaExceeded(_) => Code::ResourceExhausted,
+            ServerError::ProverTimeout(_) => Code::DeadlineExceeded,
+            ServerError::ProverUnavailable(_) => Code::Unavailable,
+            ServerError::VerificationFailed(_) => Code::FailedPrecondition,
+            ServerError::Internal(_) => Code::Internal,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ServerError::BadInput { .. } => "BAD_INPUT",
+            ServerError::UnknownProject { .. } => "UNKNOWN_PROJECT",
+            ServerError::ProjectExists { .. } => "PROJECT_EXISTS",
+            ServerError::PermissionDenied(_) => "PERMISSION_DENIED",
+            ServerError::NotConfigured(_) => "NOT_CONFIGURED",
+            ServerError::Unsupported(_) => "UNSUPPORTED",
+            ServerError::GuestPanic(_) => "GUEST_PANIC",
+            ServerError::CycleLimitExceeded(_) => "CYCLE_LIMIT_EXCEEDED",
+            ServerError::QuotaExceeded(_) => "PROVER_QUOTA_EXCEEDED",
+            ServerError::ProverTimeout(_) => "PROVER_TIMEOUT",
+            ServerError::ProverUnavailable(_) => "PROVER_UNAVAILABLE",
+            ServerError::VerificationFailed(_) => "VERIFICATION_FAILED",
+            ServerError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// Converts to a `Status` whose `ErrorInfo` metadata also carries what the
+    /// guest printed before failing.
+    pub fn with_guest_output(self, output: &GuestOutput) -> Status {
+        self.with_task_output(output, &[])
+    }
+
+    /// Like `with_guest_output`, plus the attempts that failed before this
+    /// error as a JSON list under `attempts`, shaped like `ProveAttempt`.
+    pub fn with_task_output(self, output: &GuestOutput, attempts: &[Attempt]) -> Status {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "stdout".to_string(),
+            String::from_utf8_lossy(&output.stdout()).into_owned(),
+        );
+        metadata.insert(
+            "stderr".to_string(),
+            String::from_utf8_lossy(&output.stderr()).into_owned(),
+        );
+        if output.truncated() {
+            metadata.insert("output_truncated".to_string(), "true".to_string());
+        }
+        if !attempts.is_empty() {
+            let attempts: Vec<_> = attempts
+                .iter()
+                .map(|attempt| {
+                    serde_json::json!({
+                        "attempt": attempt.attempt,
+                        "errorClass": attempt.class.to_string(),
+                        "error": attempt.error,
+                        "backoffMs": attempt.backoff.as_millis() as u64,
+                    })
+                })
+                .collect();
+            metadata.insert(
+                "attempts".to_string(),
+                serde_json::Value::from(attempts).to_string(),
+            );
+        }
+        self.into_status(metadata)
+    }
+
+    fn into_status(self, metadata: HashMap<String, String>) -> Status {
+        Status::with_error_details(self.code(), self.to_string(), self.details(metadata))
+    }
+
+    fn details(&self, mut metadata: HashMap<String, String>) -> ErrorDetails {
+        if let ServerError::UnknownProject {
+            project_id,
+            version,
+        }
+        | ServerError::ProjectExists {
+            project_id,
+            version,
+        } = self
+        {
+            metadata.insert("project_id".to_string(), project_id.clone());
+            metadata.insert("project_version".to_string(), version.clone());
+        }
+        let mut details = ErrorDetails::with_error_info(self.reason(), ERROR_DOMAIN, metadata);
+
+        match self {
+            ServerError::BadInput { field, reason } => {
+                details.add_bad_request_violation(*field, reason.clone());
+            }
+            ServerError::UnknownProject {
+                project_id,
+                version,
+            } => {
+                details.set_resource_info(
+                    "project",
+                    format!("{}@{}", project_id, version),
+                    "",
+                    self.to_string(),
+                );
+            }
+            ServerError::NotConfigured(subject) => {
+                details.add_precondition_failure_violation(
+                    "NOT_CONFIGURED",
+                    *subject,
+                    self.to_string(),
+                );
+            }
+            ServerError::VerificationFailed(reason) => {
+                details.add_precondition_failure_violation(
+                    "VERIFICATION_FAILED",
+                    "receipt",
+                    reason.clone(),
+                );
+            }
+            ServerError::Unsupported(reason) => {
+                details.add_precondition_failure_violation("UNSUPPORTED", "", reason.clone());
+            }
+            ServerError::ProverUnavailable(_) => {
+                details.set_retry_info(Some(UNAVAILABLE_RETRY_DELAY));
+            }
+            _ => {}
+        }
+        details
+    }
+}
+
+impl From<ServerError> for Status {
+    fn from(e: ServerError) -> Self {
+        e.into_status(HashMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::receipt::encode_receipt;
+    use anyhow::anyhow;
+    use risc0_zkvm::{sha::Digest, Receipt};
+    use std::io::Write;
+
+    #[test]
+    fn test_status_details() {
+        let status: Status = ServerError::bad_input("payloads", "not JSON").into();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let details = status.get_error_details();
+        assert_eq!(details.error_info().unwrap().reason, "BAD_INPUT");
+        let violation = &details.bad_request().unwrap().field_violations[0];
+        assert_eq!(violation.field, "payloads");
+        assert_eq!(violation.description, "not JSON");
+    }
+
+    #[test]
+    fn test_with_guest_output() {
+        let output = GuestOutput::default();
+        output
+            .stderr_writer()
+            .write_all(b"assertion failed")
+            .unwrap();
+
+        let status = ServerError::GuestPanic("boom".to_string()).with_guest_output(&output);
+        assert_eq!(status.code(), Code::Aborted);
+        let info = status.get_error_details().error_info().unwrap().clone();
+        assert_eq!(info.reason, "GUEST_PANIC");
+        assert_eq!(info.metadata["stderr"], "assertion failed");
+        assert_eq!(info.metadata["stdout"], "");
+    }
+
+    #[test]
+    fn test_with_task_output_attempts() {
+        let attempts = [Attempt {
+            attempt: 1,
+            class: ErrorClass::Transient,
+            error: "Bonsai request failed: service unavailable".to_string(),
+            backoff: Duration::from_millis(1000),
+        }];
+        let status = ServerError::ProverUnavailable("down".to_string())
+            .with_task_output(&GuestOutput::default(), &attempts);
+        let info = status.get_error_details().error_info().unwrap().clone();
+        let attempts: serde_json::Value = serde_json::from_str(&info.metadata["attempts"]).unwrap();
+        assert_eq!(attempts[0]["attempt"], 1);
+        assert_eq!(attempts[0]["errorClass"], "Transient");
+        assert_eq!(attempts[0]["backoffMs"], 1000);
+
+        let status =
+            ServerError::GuestPanic("boom".to_string()).with_guest_output(&GuestOutput::default());
+        let info = status.get_error_details().error_info().unwrap().clone();
+        assert!(!info.metadata.contains_key("attempts"));
+    }
+
+    #[test]
+    fn test_from_prove_error() {
+        let cases = [
+            (anyhow!("Guest panicked: bad input"), Code::Aborted),
+            (
+                anyhow!("Session limit exceeded: 5000000 cycles"),
+                Code::ResourceExhausted,
+            ),
+            (
+                anyhow!(BonsaiError::QuotaExceeded("budget".to_string())),
+                Code::ResourceExhausted,
+            ),
+            (
+                anyhow!(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionRefused,
+                    "r0vm"
+                )),
+                Code::Unavailable,
+            ),
+            (anyhow!("something else"), Code::Internal),
+        ];
+        for (e, code) in cases {
+            assert_eq!(ServerError::from_prove_error(e).code(), code);
+        }
+    }
+
+    #[test]
+    fn test_from_encode_error() {
+        let receipt: Receipt =
+            serde_json::from_str(include_str!("../tests/groth16_receipt.json")).unwrap();
+        let e = encode_receipt(&receipt, Digest::default()).unwrap_err();
+        let status: Status = ServerError::from_encode_error(e).into();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        let details = status.get_error_details();
+        assert_eq!(details.error_info().unwrap().reason, "VERIFICATION_FAILED");
+        assert_eq!(
+            details.precondition_failure().unwrap().violations[0].subject,
+            "receipt"
+        );
+
+        let e = ServerError::from_encode_error(anyhow!("serde failure"));
+        assert_eq!(e.code(), Code::Internal);
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rust_grpc::grpc::vm::vm_server::SERVICE_NAME;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+use tracing::{info, warn};
+
+use crate::core::bonsai::{BonsaiClient, BonsaiConfig};
+use crate::core::prover::LocalProver;
+
+/// How often prover backends are re-checked.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Services whose status follows the backends: `vm.VM` and the overall `""`
+/// service that default probes (k8s, grpc_health_probe) query.
+pub const SERVICES: [&str; 2] = [SERVICE_NAME, ""];
+
+/// Keeps the `vm.VM` and overall health status in step with prover backend
+/// readiness, reporting NOT_SERVING while r0vm is missing or Bonsai is
+/// unreachable.
+/// Runs until aborted; shutdown aborts it before reporting NOT_SERVING.
+pub async fn watch_backends(mut reporter: HealthReporter, bonsai: Option<BonsaiConfig>) {
+    let mut serving = None;
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let config = bonsai.clone();
+        let ready = tokio::task::spawn_blocking(move || check_backends(config.as_ref()))
+            .await
+            .context("Failed to spawn blocking task")
+            .and_then(|res| res);
+
+        let status = match &ready {
+            Ok(()) => ServingStatus::Serving,
+            Err(_) => ServingStatus::NotServing,
+        };
+        if serving != Some(status) {
+            match &ready {
+                Ok(()) => info!("Prover backends ready"),
+                Err(e) => warn!("Prover backends not ready: {:#}", e),
+            }
+            for service in SERVICES {
+                reporter.set_service_status(service, status).await;
+            }
+            serving = Some(status);
+        }
+    }
+}
+
+/// Local proving is always needed (preflight and receipt conversion), Bonsai
+/// only when configured.
+pub fn check_backends(bonsai: Option<&BonsaiConfig>) -> Result<()> {
+    LocalProver::check_available()?;
+    if let Some(config) = bonsai {
+        check_bonsai(config)?;
+    }
+    Ok(())
+}
+
+fn check_bonsai(config: &BonsaiConfig) -> Result<()> {
+    BonsaiClient::new(config)?
+        .ping()
+        .context("Bonsai is unreachable")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::bonsai_mock::{groth16_receipt, MockBonsai};
+
+    #[tokio::test]
+    async fn test_check_bonsai() {
+        let mock = MockBonsai::start(groth16_receipt()).await;
+        let config = mock.config();
+        let mut bad_key = mock.config();
+        bad_key.api_key = "wrong".to_string();
+
+        tokio::task::spawn_blocking(move || {
+            check_bonsai(&config).unwrap();
+            assert!(check_bonsai(&bad_key).is_err());
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -1,28 +1,61 @@
 use std::collections::HashMap;
-use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use std::{io::Read, str::FromStr};
 
-use ethers::abi::{encode, Token};
+use anyhow::Context;
 use flate2::read::ZlibDecoder;
 use lazy_static::lazy_static;
 use regex::Regex;
-use risc0_ethereum_contracts::groth16;
-use risc0_zkvm::InnerReceipt;
+use risc0_zkvm::{compute_image_id, sha::Digest as ImageId, Receipt};
+use rust_grpc::auth::{task_digest, AuthConfig};
 use rust_grpc::grpc::vm::{
-    vm_server::Vm, ExecuteTaskRequest, ExecuteTaskResponse, NewProjectRequest, NewProjectResponse,
+    vm_server::Vm, ConvertReceiptRequest, ConvertReceiptResponse, DeleteProjectRequest,
+    DeleteProjectResponse, ExecuteTaskRequest, ExecuteTaskResponse, GetProjectRequest,
+    GetProjectResponse, ListProjectsRequest, ListProjectsResponse, NewProjectRequest,
+    NewProjectResponse, ProjectInfo, ProveAttempt, VerifyReceiptRequest, VerifyReceiptResponse,
 };
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 use tokio::sync::RwLock;
 use tonic::{Request, Response, Status};
-use tracing::{error, info, warn};
+use tracing::{error, field, info, info_span, warn, Span};
 
+use crate::config::{Config, Limits};
+use crate::core::bonsai::BonsaiConfig;
+use crate::core::metadata::ProjectMetadata;
+use crate::core::output::GuestOutput;
+use crate::core::owner::ProjectOwners;
 use crate::core::prover::{BonsaiProver, LocalProver, Prover};
+use crate::core::receipt::{conversion_opts, decode_receipt, encode_receipt, ReceiptKind};
+use crate::core::retry::{retry, Attempt, GaveUp, RetryPolicy};
+use crate::core::session::SessionStore;
+use crate::core::store::ImageStore;
+use crate::core::version;
+use crate::metrics;
+use crate::shutdown::{Drain, TaskGuard};
+
+use super::error::ServerError;
 
 pub struct Risc0Server {
     // TODO: replace with LRU
     projects: Arc<RwLock<HashMap<ProjectKey, Project>>>,
+    images: ImageStore,
+    bonsai: Option<BonsaiConfig>,
+    retry: RetryPolicy,
+    limits: Limits,
+    // Versions kept per project; older ones are dropped on registration.
+    keep_versions: Option<usize>,
+    // When set, uploads must be signed by an owner of the project.
+    owners: Option<ProjectOwners>,
+    // When set, ExecuteTask callers must present a token or sequencer signature.
+    auth: Option<Arc<AuthConfig>>,
+    // When set, Bonsai sessions are checkpointed so retried tasks resume them.
+    sessions: Option<SessionStore>,
+    // Off only in tests whose receipt fixture has no matching ELF.
+    check_image_ids: bool,
+    drain: Arc<Drain>,
 }
 
 #[derive(Clone)]
@@ -30,18 +63,45 @@ struct Project {
     pub project_id: String,
     pub project_version: String,
     // TODO: share prover across threads
-    pub elf: Vec<u8>,
+    // Shared through `images` with every project that has the same image.
+    pub elf: Arc<[u8]>,
     pub image_id: Vec<u32>,
+    pub metadata: ProjectMetadata,
+    // Set once the ELF has been uploaded to Bonsai.
+    pub bonsai_image_id: Option<String>,
+    // Cycles used by the registration preflight, for scheduling.
+    pub preflight_cycles: Option<u64>,
+    // Metadata exactly as submitted, returned by GetProject.
+    pub raw_metadata: Vec<u8>,
+    pub registered_at: SystemTime,
+    pub stats: Arc<TaskStats>,
+}
+
+#[derive(Default)]
+struct TaskStats {
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl TaskStats {
+    fn record(&self, ok: bool) {
+        let counter = if ok { &self.succeeded } else { &self.failed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 #[derive(Hash, Eq, PartialEq, Clone)]
 struct ProjectKey([u8; 32]);
 
 impl ProjectKey {
-    fn new(project_id: &String, version: &String) -> Self {
+    // Each field is hashed behind its length so ("a", "bc") and ("ab", "c")
+    // get different keys. Keep in step with projectKey in gnark-server.
+    fn new(project_id: &str, version: &str) -> Self {
         let mut hasher = Sha256::new();
-        hasher.update(project_id);
-        hasher.update(version);
+        for field in [project_id, version] {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field);
+        }
         let result = hasher.finalize();
         let mut hash = [0u8; 32];
         hash.copy_from_slice(&result);
@@ -57,25 +117,452 @@ lazy_static! {
 
 impl Risc0Server {
     pub fn new() -> Self {
-        Risc0Server {
+        Risc0Server::from_config(&Config::default()).expect("default config is valid")
+    }
+
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let bonsai = match &config.bonsai {
+            Some(settings) => Some(settings.build()?),
+            None => None,
+        };
+
+        let keep_versions = match config.projects.keep_versions {
+            Some(0) => anyhow::bail!("projects.keep_versions must be at least 1"),
+            keep => keep,
+        };
+
+        let owners = match &config.projects.owners_file {
+            Some(path) => Some(ProjectOwners::from_file(path)?),
+            None => None,
+        };
+
+        let auth = config.auth.build()?;
+
+        let sessions = match config.bonsai.as_ref().and_then(|b| b.session_dir.as_ref()) {
+            Some(dir) => {
+                let store = SessionStore::open(dir)?;
+                if !store.is_empty() {
+                    info!("{} Bonsai session(s) saved for resumption", store.len());
+                }
+                Some(store)
+            }
+            None => None,
+        };
+
+        Ok(Risc0Server {
             projects: Arc::new(RwLock::new(HashMap::new())),
+            images: ImageStore::new(),
+            bonsai,
+            retry: config.retry.policy(),
+            limits: config.limits.clone(),
+            keep_versions,
+            owners,
+            auth: auth.map(Arc::new),
+            sessions,
+            check_image_ids: true,
+            drain: Drain::new(),
+        })
+    }
+
+    pub fn with_bonsai(mut self, config: BonsaiConfig) -> Self {
+        self.bonsai = Some(config);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Accepts uploads whose image ID does not match their ELF.
+    #[cfg(test)]
+    fn without_image_id_check(mut self) -> Self {
+        self.check_image_ids = false;
+        self
+    }
+
+    pub fn with_project_owners(mut self, owners: ProjectOwners) -> Self {
+        self.owners = Some(owners);
+        self
+    }
+
+    pub fn with_auth(mut self, config: AuthConfig) -> Self {
+        self.auth = Some(Arc::new(config));
+        self
+    }
+
+    /// Tracks in-flight tasks, for graceful shutdown.
+    pub fn drain(&self) -> Arc<Drain> {
+        self.drain.clone()
+    }
+
+    /// The Bonsai backend, for health checks.
+    pub fn bonsai(&self) -> Option<BonsaiConfig> {
+        self.bonsai.clone()
+    }
+
+    /// The caller allowlist, for installing the matching interceptor.
+    pub fn auth(&self) -> Option<Arc<AuthConfig>> {
+        self.auth.clone()
+    }
+
+    /// Keeps only the newest `keep` versions of each project.
+    pub fn with_keep_versions(mut self, keep: usize) -> Self {
+        self.keep_versions = Some(keep);
+        self
+    }
+
+    fn extract_data<T: FromStr>(&self, regex: &Regex, text: &str) -> Option<Vec<T>> {
+        let captures = regex.captures(text)?;
+        // let prefix = captures[1].to_string();
+        let data_str = &captures[2];
+        data_str
+            .split(',')
+            .map(|num| num.trim().parse::<T>().ok())
+            .collect()
+    }
+
+    // `version` may also be "latest" or a semver range; the returned project
+    // carries the version it resolved to.
+    async fn find_project(&self, project_id: &str, version: &str) -> Result<Project, Status> {
+        let map = self.projects.read().await;
+        if let Some(project) = map.get(&ProjectKey::new(project_id, version)) {
+            return Ok(project.clone());
+        }
+        let versions = Self::versions_of(&map, project_id);
+        version::resolve(version, &versions)
+            .and_then(|resolved| map.get(&ProjectKey::new(project_id, resolved)))
+            .cloned()
+            .ok_or_else(|| {
+                ServerError::UnknownProject {
+                    project_id: project_id.to_string(),
+                    version: version.to_string(),
+                }
+                .into()
+            })
+    }
+
+    fn versions_of<'a>(map: &'a HashMap<ProjectKey, Project>, project_id: &str) -> Vec<&'a str> {
+        map.values()
+            .filter(|p| p.project_id == project_id)
+            .map(|p| p.project_version.as_str())
+            .collect()
+    }
+
+    // Drops the oldest versions of `project_id` beyond the retention limit,
+    // never the one just registered.
+    fn prune_versions(
+        &self,
+        map: &mut HashMap<ProjectKey, Project>,
+        project_id: &str,
+        registered: &str,
+    ) {
+        let Some(keep) = self.keep_versions else {
+            return;
+        };
+        let expired: Vec<String> = version::expired(&Self::versions_of(map, project_id), keep)
+            .into_iter()
+            .filter(|v| *v != registered)
+            .map(str::to_string)
+            .collect();
+        for v in expired {
+            map.remove(&ProjectKey::new(project_id, &v));
+            info!("Project pruned(id{}, version{})", project_id, v);
+        }
+    }
+
+    // Refuses new proving work once shutdown has begun.
+    fn start_task(&self) -> Result<TaskGuard, Status> {
+        self.drain.start_task().ok_or_else(|| {
+            ServerError::ProverUnavailable("server is shutting down".to_string()).into()
+        })
+    }
+
+    fn bonsai_config(&self) -> Result<BonsaiConfig, Status> {
+        self.bonsai
+            .clone()
+            .ok_or_else(|| ServerError::NotConfigured("Bonsai").into())
+    }
+
+    fn project_exists(&self, req: &NewProjectRequest) -> Status {
+        ServerError::ProjectExists {
+            project_id: req.project_id.clone(),
+            version: req.project_version.clone(),
+        }
+        .into()
+    }
+
+    async fn set_bonsai_image_id(&self, project_id: &String, version: &String, image_id: String) {
+        let mut map = self.projects.write().await;
+        if let Some(project) = map.get_mut(&ProjectKey::new(project_id, version)) {
+            project.bonsai_image_id = Some(image_id);
+        }
+    }
+
+    async fn run_task(
+        &self,
+        req: ExecuteTaskRequest,
+        project: Project,
+    ) -> Result<ExecuteTaskResponse, Status> {
+        let image_id = project.image_id()?;
+
+        // TODO move to guest method
+        // param = {"private_input":"14", "public_input":"3,34", "receipt_type":"Snark"}
+        // let input_datas = json!(datas).to_string();
+        let v: Value = serde_json::from_slice(&req.payloads[0]).map_err(|e| {
+            ServerError::bad_input("payloads", format!("payloads[0] is not JSON: {}", e))
+        })?;
+        let bonsai = match v.get("receipt_type").and_then(Value::as_str) {
+            Some("Snark") => Some(self.bonsai_config()?),
+            _ => None,
+        };
+        let enable_profiler = v
+            .get("enable_profiler")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        if bonsai.is_some() && enable_profiler {
+            return Err(ServerError::bad_input(
+                "enable_profiler",
+                "profiling is only available for local proving",
+            )
+            .into());
+        }
+        if bonsai.is_some() && !project.metadata.env.is_empty() {
+            return Err(ServerError::bad_input(
+                "receipt_type",
+                "Snark tasks cannot use guest environment variables",
+            )
+            .into());
+        }
+
+        let data = req
+            .payloads
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                String::from_utf8(v.clone()).map_err(|e| {
+                    ServerError::bad_input(
+                        "payloads",
+                        format!("payloads[{}] is not UTF-8: {}", i, e),
+                    )
+                })
+            })
+            .collect::<Result<Vec<String>, _>>()?;
+
+        let has_bonsai_image = project.bonsai_image_id.is_some();
+        let bonsai_image_id = project
+            .bonsai_image_id
+            .clone()
+            .unwrap_or_else(|| image_id.to_string());
+        let journal_schema = project.metadata.journal_schema.clone();
+        let input_bindings = project.metadata.input_bindings.clone();
+        let policy = self.retry.clone();
+        let resolved_version = project.project_version.clone();
+        let session_limit = self.limits.session_limit;
+        let sessions = self.sessions.clone().map(|store| {
+            let payloads: Vec<&[u8]> = req.payloads.iter().map(Vec::as_slice).collect();
+            let key = task_digest(&req.project_id, &resolved_version, &req.task_id, &payloads);
+            (store, hex::encode(key))
+        });
+        let output = GuestOutput::new(self.limits.guest_output_limit);
+        let task_output = output.clone();
+        let span = info_span!("prove", backend = field::Empty);
+        let outcome = tokio::task::spawn_blocking(move || -> anyhow::Result<ProveOutcome> {
+            let _enter = span.enter();
+            // TODO: move prover initialization to new_project
+            let guest_env = project.metadata.guest_env();
+            let profile_dir = if enable_profiler {
+                Some(tempfile::tempdir()?)
+            } else {
+                None
+            };
+            let profile_path = profile_dir
+                .as_ref()
+                .map(|dir| dir.path().join("profile.pb"));
+            let mut uploaded_image_id = None;
+            let backend = if bonsai.is_some() { "bonsai" } else { "local" };
+            span.record("backend", backend);
+            let prover: Box<dyn Prover> = match bonsai {
+                Some(config) => {
+                    let mut prover =
+                        BonsaiProver::new(project.elf.clone(), bonsai_image_id, &config)?
+                            .with_project_id(&project.project_id)
+                            .with_env(guest_env);
+                    if let Some((store, key)) = sessions {
+                        prover = prover.with_session_store(store, key);
+                    }
+                    // Projects registered without groth16 upload on first use.
+                    if !has_bonsai_image {
+                        uploaded_image_id = Some(prover.upload_image()?);
+                    }
+                    Box::new(prover)
+                }
+                None => {
+                    let mut prover = LocalProver::new(project.elf.clone())
+                        .with_env(guest_env)
+                        .with_session_limit(session_limit);
+                    if let Some(path) = &profile_path {
+                        prover = prover.with_profiler(path);
+                    }
+                    Box::new(prover)
+                }
+            };
+
+            let started = Instant::now();
+            let (proof, attempts) = retry(&policy, || {
+                task_output.clear();
+                prover.prove(data.clone(), &task_output)
+            });
+            metrics::PROOF_DURATION
+                .with_label_values(&[&project.project_id, backend])
+                .observe(started.elapsed().as_secs_f64());
+            task_output.log();
+            let proof = proof.map_err(|e| {
+                e.context(GaveUp {
+                    attempts: attempts.clone(),
+                })
+            })?;
+            if let Some(cycles) = proof.cycles {
+                metrics::TASK_CYCLES
+                    .with_label_values(&[&project.project_id])
+                    .observe(cycles as f64);
+            }
+            let profile = match &profile_path {
+                Some(path) => Some(std::fs::read(path).context("Failed to read guest profile")?),
+                None => None,
+            };
+            Ok(ProveOutcome {
+                receipt: proof.receipt,
+                uploaded_image_id,
+                attempts,
+                profile,
+            })
+        })
+        .await
+        .map_err(|e| ServerError::internal("Failed to spawn blocking task", e))?
+        .map_err(|e| {
+            let attempts = e
+                .downcast_ref::<GaveUp>()
+                .map(|gave_up| gave_up.attempts.clone())
+                .unwrap_or_default();
+            ServerError::from_prove_error(e).with_task_output(&output, &attempts)
+        })?;
+
+        let ProveOutcome {
+            receipt,
+            uploaded_image_id,
+            attempts,
+            profile,
+        } = outcome;
+
+        if let Some(bonsai_image_id) = uploaded_image_id {
+            self.set_bonsai_image_id(&req.project_id, &resolved_version, bonsai_image_id)
+                .await;
+        }
+
+        info!("receipt: {:?}", receipt);
+
+        let result = info_span!("encode")
+            .in_scope(|| encode_receipt(&receipt, image_id))
+            .map_err(|e| {
+                error!("Failed to encode receipt: {:#}", e);
+                ServerError::from_encode_error(e)
+            })?;
+
+        info_span!("verify").in_scope(|| {
+            for binding in &input_bindings {
+                binding
+                    .check(&v, &receipt.journal.bytes, journal_schema.as_ref())
+                    .map_err(|e| {
+                        warn!("Public input binding failed: {:#}", e);
+                        ServerError::VerificationFailed(format!("{:#}", e))
+                    })?;
+            }
+            Ok::<_, ServerError>(())
+        })?;
+
+        // The proof is valid either way, so a schema that does not fit the
+        // journal only costs the caller the decoded view.
+        let journal_json = match journal_schema.map(|schema| schema.decode(&receipt.journal.bytes))
+        {
+            Some(Ok(decoded)) => decoded.to_string(),
+            Some(Err(e)) => {
+                warn!("Journal does not match schema: {:#}", e);
+                "".to_string()
+            }
+            None => "".to_string(),
+        };
+
+        Ok(ExecuteTaskResponse {
+            result: result,
+            attempts: attempts.into_iter().map(ProveAttempt::from).collect(),
+            stdout: output.stdout(),
+            stderr: output.stderr(),
+            output_truncated: output.truncated(),
+            profile: profile.unwrap_or_default(),
+            journal_json,
+            resolved_version,
+        })
+    }
+}
+
+/// Tags the request span from `telemetry::request_span`, so every span and
+/// event under it carries the project and task.
+fn record_request(project_id: &str, version: &str, task_id: Option<&[u8]>) {
+    let span = Span::current();
+    span.record("project_id", project_id);
+    span.record("project_version", version);
+    if let Some(task_id) = task_id {
+        span.record("task_id", hex::encode(task_id).as_str());
+    }
+}
+
+/// What the blocking prove step hands back to `execute_task`.
+struct ProveOutcome {
+    receipt: Receipt,
+    // Set if the task had to upload the image to Bonsai itself.
+    uploaded_image_id: Option<String>,
+    attempts: Vec<Attempt>,
+    profile: Option<Vec<u8>>,
+}
+
+impl From<Attempt> for ProveAttempt {
+    fn from(attempt: Attempt) -> Self {
+        ProveAttempt {
+            attempt: attempt.attempt,
+            error_class: attempt.class.to_string(),
+            error: attempt.error,
+            backoff_ms: attempt.backoff.as_millis() as u64,
         }
     }
+}
+
+impl Project {
+    fn image_id(&self) -> Result<ImageId, Status> {
+        ImageId::try_from(self.image_id.as_slice())
+            .map_err(|_| ServerError::Internal("Invalid project image ID".to_string()).into())
+    }
 
-    fn extract_data<T: FromStr + Debug>(&self, regex: &Regex, text: &str) -> Option<Vec<T>>
-    where
-        T::Err: Debug,
-    {
-        if let Some(captures) = regex.captures(text) {
-            // let prefix = captures[1].to_string();
-            let data_str = &captures[2];
-            let data: Vec<T> = data_str
-                .split(',')
-                .map(|num| num.trim().parse::<T>().unwrap())
-                .collect();
-            return Some(data);
+    fn info(&self) -> ProjectInfo {
+        let registered_at = self
+            .registered_at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        ProjectInfo {
+            project_id: self.project_id.clone(),
+            project_version: self.project_version.clone(),
+            image_id: self
+                .image_id()
+                .map(|id| id.as_bytes().to_vec())
+                .unwrap_or_default(),
+            elf_size: self.elf.len() as u64,
+            metadata: self.raw_metadata.clone(),
+            registered_at,
+            tasks_succeeded: self.stats.succeeded.load(Ordering::Relaxed),
+            tasks_failed: self.stats.failed.load(Ordering::Relaxed),
+            preflight_cycles: self.preflight_cycles.unwrap_or_default(),
         }
-        None
     }
 }
 
@@ -85,35 +572,164 @@ impl Vm for Risc0Server {
         &self,
         request: Request<NewProjectRequest>,
     ) -> Result<Response<NewProjectResponse>, Status> {
+        if let Some(auth) = &self.auth {
+            auth.require_token(&request)?;
+        }
         let req = request.get_ref();
+        record_request(&req.project_id, &req.project_version, None);
+        let key = ProjectKey::new(&req.project_id, &req.project_version);
+
+        if req.project_version == version::LATEST {
+            return Err(ServerError::bad_input(
+                "project_version",
+                "\"latest\" is reserved for resolving the newest version",
+            )
+            .into());
+        }
+        if let Some(owners) = &self.owners {
+            owners
+                .verify(
+                    &req.project_id,
+                    &req.project_version,
+                    &req.binary,
+                    &req.metadata,
+                    &req.signature,
+                )
+                .map_err(|e| {
+                    warn!("Upload rejected(id{}): {:#}", req.project_id, e);
+                    ServerError::PermissionDenied(format!("{:#}", e))
+                })?;
+        }
+        if !req.replace && self.projects.read().await.contains_key(&key) {
+            return Err(self.project_exists(req));
+        }
 
         // Decompress the binary data
-        let compressed_data = req.binary.as_slice();
-        let mut decoder = ZlibDecoder::new(compressed_data);
-        let mut content = Vec::new();
-        decoder
-            .read_to_end(&mut content)
-            .map_err(|e| Status::internal(format!("Failed to decompress data: {}", e)))?;
-        let content_str = String::from_utf8(content).unwrap();
-
-        let elf_data = self
-            .extract_data::<u8>(&ELF_RE, &content_str)
-            .ok_or(Status::internal("Failed to extract ELF data"))?;
-        let id_data = self
-            .extract_data::<u32>(&IMAGE_ID_RE, &content_str)
-            .ok_or(Status::internal("Failed to extract ID data"))?;
+        let content_str = info_span!("decompress").in_scope(|| {
+            let compressed_data = req.binary.as_slice();
+            let mut decoder = ZlibDecoder::new(compressed_data);
+            let mut content = Vec::new();
+            decoder.read_to_end(&mut content).map_err(|e| {
+                ServerError::bad_input("binary", format!("Failed to decompress: {}", e))
+            })?;
+            String::from_utf8(content)
+                .map_err(|e| ServerError::bad_input("binary", format!("Invalid UTF-8: {}", e)))
+        })?;
+
+        let (elf_data, id_data) = info_span!("parse_elf").in_scope(|| {
+            let elf_data = self
+                .extract_data::<u8>(&ELF_RE, &content_str)
+                .ok_or_else(|| ServerError::bad_input("binary", "Failed to extract ELF data"))?;
+            let id_data = self
+                .extract_data::<u32>(&IMAGE_ID_RE, &content_str)
+                .ok_or_else(|| ServerError::bad_input("binary", "Failed to extract ID data"))?;
+            Ok::<_, ServerError>((elf_data, id_data))
+        })?;
+        let metadata = ProjectMetadata::from_bytes(&req.metadata)
+            .map_err(|e| ServerError::bad_input("metadata", format!("{:#}", e)))?;
+        let image_id = ImageId::try_from(id_data.as_slice())
+            .map_err(|_| ServerError::bad_input("binary", "image ID must be 8 words"))?;
+        // The store shares ELFs by image ID, so the ID must really be this ELF's.
+        // Hashing a large ELF takes a while, so it runs off the async workers.
+        let elf_data = if self.check_image_ids {
+            let span = info_span!("image_id");
+            let (elf_data, computed) = tokio::task::spawn_blocking(move || {
+                let _enter = span.enter();
+                let computed = compute_image_id(&elf_data);
+                (elf_data, computed)
+            })
+            .await
+            .map_err(|e| ServerError::internal("Failed to spawn blocking task", e))?;
+            let computed = computed
+                .map_err(|e| ServerError::bad_input("binary", format!("Invalid ELF: {:#}", e)))?;
+            if computed != image_id {
+                return Err(ServerError::bad_input(
+                    "binary",
+                    format!(
+                        "image ID {} does not match the ELF's image ID {}",
+                        image_id, computed
+                    ),
+                )
+                .into());
+            }
+            elf_data
+        } else {
+            elf_data
+        };
+        let elf_data = self.images.intern(&req.project_id, image_id, elf_data);
+
+        let preflight_cycles = if metadata.preflight_payloads.is_empty() {
+            None
+        } else {
+            let prover = LocalProver::new(elf_data.clone())
+                .with_env(metadata.guest_env())
+                .with_session_limit(self.limits.session_limit);
+            let data = metadata.preflight_data();
+            let output = GuestOutput::new(self.limits.guest_output_limit);
+            let preflight_output = output.clone();
+            let span = info_span!("execute");
+            let cycles = tokio::task::spawn_blocking(move || {
+                let _enter = span.enter();
+                let cycles = prover.execute(data, &preflight_output);
+                preflight_output.log();
+                cycles
+            })
+            .await
+            .map_err(|e| ServerError::internal("Failed to spawn blocking task", e))?
+            .map_err(|e| {
+                warn!("Preflight failed(id{}): {:#}", req.project_id, e);
+                ServerError::from_prove_error(e.context("Preflight failed"))
+                    .with_guest_output(&output)
+            })?;
+
+            info!("Preflight passed(id{}, cycles{})", req.project_id, cycles);
+            Some(cycles)
+        };
+
+        let bonsai_image_id = if metadata.groth16 {
+            let config = self.bonsai_config()?;
+            let elf = elf_data.clone();
+            let project_id = req.project_id.clone();
+            let span = info_span!("upload_image");
+            let image_id = tokio::task::spawn_blocking(move || {
+                let _enter = span.enter();
+                BonsaiProver::new(elf, image_id.to_string(), &config)?
+                    .with_project_id(&project_id)
+                    .upload_image()
+            })
+            .await
+            .map_err(|e| ServerError::internal("Failed to spawn blocking task", e))?
+            .map_err(ServerError::from_prove_error)?;
+
+            info!("Image uploaded to Bonsai(image{})", image_id);
+            Some(image_id)
+        } else {
+            None
+        };
 
         {
             let mut map = self.projects.write().await;
+            // Re-checked under the write lock in case of a concurrent upload.
+            if !req.replace && map.contains_key(&key) {
+                return Err(self.project_exists(req));
+            }
             map.insert(
-                ProjectKey::new(&req.project_id, &req.project_version),
+                key,
                 Project {
                     project_id: req.project_id.clone(),
                     project_version: req.project_version.clone(),
                     elf: elf_data,
                     image_id: id_data,
+                    metadata,
+                    bonsai_image_id,
+                    preflight_cycles,
+                    raw_metadata: req.metadata.clone(),
+                    registered_at: SystemTime::now(),
+                    stats: Arc::new(TaskStats::default()),
                 },
             );
+            self.prune_versions(&mut map, &req.project_id, &req.project_version);
+            metrics::PROJECTS.set(map.len() as i64);
         }
 
         info!(
@@ -121,7 +737,9 @@ impl Vm for Risc0Server {
             req.project_id, req.project_version
         );
 
-        Ok(Response::new(NewProjectResponse {}))
+        Ok(Response::new(NewProjectResponse {
+            preflight_cycles: preflight_cycles.unwrap_or_default(),
+        }))
     }
 
     async fn execute_task(
@@ -129,65 +747,244 @@ impl Vm for Risc0Server {
         request: Request<ExecuteTaskRequest>,
     ) -> Result<Response<ExecuteTaskResponse>, Status> {
         info!("risc0_server execute_task");
+        let _task = self.start_task()?;
+        let req = request.get_ref();
+        record_request(&req.project_id, &req.project_version, Some(&req.task_id));
+
+        if let Some(auth) = &self.auth {
+            auth.authorize_task(&request, &[], || {
+                let payloads: Vec<&[u8]> = req.payloads.iter().map(Vec::as_slice).collect();
+                task_digest(
+                    &req.project_id,
+                    &req.project_version,
+                    &req.task_id,
+                    &payloads,
+                )
+            })?;
+        }
 
         let req = request.into_inner();
 
         if req.payloads.is_empty() {
-            return Err(Status::invalid_argument("data is empty"));
+            return Err(
+                ServerError::bad_input("payloads", "at least one payload is required").into(),
+            );
         }
 
-        let project = {
-            let map = self.projects.read().await;
-            map.get(&ProjectKey::new(&req.project_id, &req.project_version))
-                .cloned()
+        let project = self
+            .find_project(&req.project_id, &req.project_version)
+            .await?;
+        let stats = project.stats.clone();
+        let (project_id, version) = (project.project_id.clone(), project.project_version.clone());
+        let queued = metrics::Queued::new(&project_id);
+        let result = self.run_task(req, project).await;
+        drop(queued);
+        stats.record(result.is_ok());
+        metrics::record_task(&project_id, &version, &result);
+        result.map(Response::new)
+    }
+
+    async fn convert_receipt(
+        &self,
+        request: Request<ConvertReceiptRequest>,
+    ) -> Result<Response<ConvertReceiptResponse>, Status> {
+        info!("risc0_server convert_receipt");
+        if let Some(auth) = &self.auth {
+            auth.require_token(&request)?;
         }
-        .ok_or_else(|| Status::not_found(format!("{} not found", req.project_id)))?;
+        let _task = self.start_task()?;
 
-        // TODO move to guest method
-        // param = {"private_input":"14", "public_input":"3,34", "receipt_type":"Snark"}
-        // let input_datas = json!(datas).to_string();
-        let v: Value = serde_json::from_slice(&req.payloads[0]).unwrap();
+        let req = request.into_inner();
+        record_request(&req.project_id, &req.project_version, None);
 
-        let receipt = tokio::task::spawn_blocking(move || {
-            // TODO: move prover initialization to new_project
-            let prover: Box<dyn Prover> = match v.get("receipt_type") {
-                Some(receipt_type) => match receipt_type.as_str().unwrap() {
-                    "Stark" => Box::new(LocalProver::new(&project.elf)),
-                    "Snark" => Box::new(BonsaiProver::new(&project.elf)),
-                    _ => Box::new(LocalProver::new(&project.elf)),
-                },
-                None => Box::new(LocalProver::new(&project.elf)),
-            };
+        let target = ReceiptKind::from_str(&req.receipt_type)
+            .map_err(|e| ServerError::bad_input("receipt_type", e))?;
+        // Bonsai only wraps its own sessions in a SNARK, and a local Groth16
+        // proof is far too heavy to run inside a request.
+        if target == ReceiptKind::Groth16 {
+            return Err(ServerError::Unsupported(
+                "no SNARK backend can convert an uploaded receipt to Groth16; \
+                 prove Groth16 receipts with ExecuteTask on a project registered with \
+                 groth16 metadata"
+                    .to_string(),
+            )
+            .into());
+        }
+
+        let project = self
+            .find_project(&req.project_id, &req.project_version)
+            .await?;
+        let image_id = project.image_id()?;
+        let resolved_version = project.project_version.clone();
+
+        let receipt: Receipt = serde_json::from_slice(&req.receipt)
+            .map_err(|e| ServerError::bad_input("receipt", format!("Failed to parse: {}", e)))?;
+        info_span!("verify")
+            .in_scope(|| receipt.verify(image_id))
+            .map_err(|e| ServerError::VerificationFailed(e.to_string()))?;
 
-            let data: Vec<String> = req
-                .payloads
-                .clone()
-                .into_iter()
-                .map(|v| String::from_utf8(v).expect("Invalid UTF-8 sequence"))
-                .collect();
+        let opts = conversion_opts(ReceiptKind::of(&receipt), target)
+            .map_err(|e| ServerError::bad_input("receipt_type", e))?;
 
-            prover.prove(data)
+        let span = info_span!("prove", backend = "local");
+        let converted = tokio::task::spawn_blocking(move || {
+            let _enter = span.enter();
+            LocalProver::new(project.elf.clone()).compress(&receipt, &opts)
         })
         .await
-        .map_err(|e| Status::internal(format!("Failed to spawn blocking task: {}", e)))?
-        .map_err(|e| Status::internal(format!("Failed to prove: {}", e)))?;
+        .map_err(|e| ServerError::internal("Failed to spawn blocking task", e))?
+        .map_err(|e| ServerError::internal("Failed to convert receipt", format!("{:#}", e)))?;
 
-        info!("receipt: {:?}", receipt);
+        info!(
+            "Receipt converted(id{}, version{}, type{})",
+            req.project_id,
+            resolved_version,
+            target.as_str()
+        );
+
+        let result = info_span!("encode")
+            .in_scope(|| encode_receipt(&converted, image_id))
+            .map_err(|e| {
+                error!("Failed to encode receipt: {:#}", e);
+                ServerError::from_encode_error(e)
+            })?;
+
+        Ok(Response::new(ConvertReceiptResponse {
+            result,
+            resolved_version,
+        }))
+    }
+
+    async fn verify_receipt(
+        &self,
+        request: Request<VerifyReceiptRequest>,
+    ) -> Result<Response<VerifyReceiptResponse>, Status> {
+        info!("risc0_server verify_receipt");
+
+        let req = request.into_inner();
+        record_request(&req.project_id, &req.project_version, None);
+
+        let mut resolved_version = "".to_string();
+        let image_id = if !req.image_id.is_empty() {
+            ImageId::try_from(req.image_id.as_slice())
+                .map_err(|_| ServerError::bad_input("image_id", "must be 32 bytes"))?
+        } else if !req.project_id.is_empty() {
+            let project = self
+                .find_project(&req.project_id, &req.project_version)
+                .await?;
+            resolved_version = project.project_version.clone();
+            project.image_id()?
+        } else {
+            return Err(ServerError::bad_input(
+                "image_id",
+                "either image ID or project ID is required",
+            )
+            .into());
+        };
+
+        let decoded = info_span!("decode").in_scope(|| decode_receipt(&req.receipt, image_id));
+        let receipt = match decoded {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                return Ok(Response::new(VerifyReceiptResponse {
+                    valid: false,
+                    receipt_type: "".to_string(),
+                    journal: vec![],
+                    error: format!("Failed to decode receipt: {}", e),
+                    resolved_version,
+                }))
+            }
+        };
+
+        let error = match info_span!("verify").in_scope(|| receipt.verify(image_id)) {
+            Ok(()) => "".to_string(),
+            Err(e) => {
+                warn!("Receipt verification failed: {}", e);
+                e.to_string()
+            }
+        };
+
+        Ok(Response::new(VerifyReceiptResponse {
+            valid: error.is_empty(),
+            receipt_type: ReceiptKind::of(&receipt).as_str().to_string(),
+            journal: receipt.journal.bytes,
+            error,
+            resolved_version,
+        }))
+    }
+
+    async fn list_projects(
+        &self,
+        request: Request<ListProjectsRequest>,
+    ) -> Result<Response<ListProjectsResponse>, Status> {
+        let req = request.into_inner();
+
+        let map = self.projects.read().await;
+        let mut projects: Vec<ProjectInfo> = map
+            .values()
+            .filter(|p| req.project_id.is_empty() || p.project_id == req.project_id)
+            .map(Project::info)
+            .collect();
+        projects.sort_by(|a, b| {
+            (&a.project_id, &a.project_version).cmp(&(&b.project_id, &b.project_version))
+        });
+
+        Ok(Response::new(ListProjectsResponse { projects }))
+    }
+
+    async fn get_project(
+        &self,
+        request: Request<GetProjectRequest>,
+    ) -> Result<Response<GetProjectResponse>, Status> {
+        let req = request.into_inner();
 
-        // let mut result = receipt.as_bytes().to_vec();
-        let mut result = serde_json::to_vec(&receipt)
-            .map_err(|e| Status::internal(format!("Failed to serialize receipt: {}", e)))?;
-        // let risc_receipt: Receipt = serde_json::from_str(&receipt).unwrap();
-        if matches!(receipt.inner, InnerReceipt::Groth16(_)) {
-            let seal = groth16::encode(receipt.inner.groth16().unwrap().seal.clone()).unwrap();
-            let journal = receipt.journal.bytes.clone();
+        let project = self
+            .find_project(&req.project_id, &req.project_version)
+            .await?;
+
+        Ok(Response::new(GetProjectResponse {
+            project: Some(project.info()),
+        }))
+    }
+
+    async fn delete_project(
+        &self,
+        request: Request<DeleteProjectRequest>,
+    ) -> Result<Response<DeleteProjectResponse>, Status> {
+        if let Some(auth) = &self.auth {
+            auth.require_token(&request)?;
+        }
+        let req = request.into_inner();
 
-            let tokens = vec![Token::Bytes(seal), Token::Bytes(journal)];
+        if let Some(owners) = &self.owners {
+            owners
+                .verify_delete(&req.project_id, &req.project_version, &req.signature)
+                .map_err(|e| {
+                    warn!("Delete rejected(id{}): {:#}", req.project_id, e);
+                    ServerError::PermissionDenied(format!("{:#}", e))
+                })?;
+        }
 
-            result = encode(&tokens);
+        let removed = {
+            let mut map = self.projects.write().await;
+            let removed = map.remove(&ProjectKey::new(&req.project_id, &req.project_version));
+            metrics::PROJECTS.set(map.len() as i64);
+            removed
+        };
+        if removed.is_none() {
+            return Err(ServerError::UnknownProject {
+                project_id: req.project_id,
+                version: req.project_version,
+            }
+            .into());
         }
 
-        Ok(Response::new(ExecuteTaskResponse { result: result }))
+        info!(
+            "Project deleted(id{}, version{})",
+            req.project_id, req.project_version
+        );
+
+        Ok(Response::new(DeleteProjectResponse {}))
     }
 }
 
@@ -229,10 +1026,13 @@ impl Vm for Risc0Server {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tests::bonsai_mock::{groth16_receipt, MockBonsai, MockMode};
     use ethers::abi::decode;
     use flate2::write::ZlibEncoder;
     use flate2::Compression;
+    use risc0_zkvm::sha::Digestible;
     use std::io::Write;
+    use tonic_types::StatusExt;
 
     fn create_dummy_elf() -> Vec<u8> {
         include_bytes!("../tests/hello_guest").to_vec()
@@ -240,19 +1040,89 @@ mod tests {
     }
 
     fn create_compressed_binary() -> Vec<u8> {
+        create_compressed_binary_with_id(&[
+            1729087496, 1782151309, 3201877536, 1628959901, 2308880694, 3762575800, 943710734,
+            1150869179,
+        ])
+    }
+
+    fn create_compressed_binary_with_id(image_id: &[u32]) -> Vec<u8> {
         let binary = create_dummy_elf();
         let content = format!(
             r#"
         pub const TEST_ELF: &[u8] = &{:?};
-        pub const TEST_ID: [u32; 8] = [1729087496, 1782151309, 3201877536, 1628959901, 2308880694, 3762575800, 943710734, 1150869179];
+        pub const TEST_ID: [u32; 8] = {:?};
         "#,
-            binary,
+            binary, image_id,
         );
         let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
         encoder.write_all(content.as_bytes()).unwrap();
         encoder.finish().unwrap()
     }
 
+    fn new_project_request(version: &str, replace: bool) -> NewProjectRequest {
+        NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: version.to_string(),
+            binary: create_compressed_binary(),
+            metadata: br#"{"env": {"MODE": "test"}}"#.to_vec(),
+            replace,
+            signature: vec![],
+        }
+    }
+
+    /// A task for test1 1.0 proving the hello guest's sample input into a
+    /// `receipt_type` receipt.
+    fn execute_task_request(receipt_type: &str) -> ExecuteTaskRequest {
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": receipt_type
+        });
+        ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: vec![],
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        }
+    }
+
+    #[test]
+    fn test_project_key_no_collision() {
+        let pairs = [
+            ("a", "bc"),
+            ("ab", "c"),
+            ("abc", ""),
+            ("", "abc"),
+            ("a\0", "bc"),
+            ("a", "\0bc"),
+        ];
+        let keys: std::collections::HashSet<[u8; 32]> = pairs
+            .iter()
+            .map(|(id, version)| ProjectKey::new(id, version).0)
+            .collect();
+        assert_eq!(keys.len(), pairs.len());
+        assert!(ProjectKey::new("a", "bc") == ProjectKey::new("a", "bc"));
+        // The same vector is checked by TestProjectKey in gnark-server.
+        assert_eq!(
+            hex::encode(ProjectKey::new("a", "bc").0),
+            "9a8acca1b6c6c0befd3fbc756aed625da998c998f7252e738c4ef061906b9b21"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_project_collision() {
+        let server = Risc0Server::new();
+        for (id, version) in [("a", "bc"), ("ab", "c")] {
+            let request = NewProjectRequest {
+                project_id: id.to_string(),
+                ..new_project_request(version, false)
+            };
+            server.new_project(Request::new(request)).await.unwrap();
+        }
+        assert_eq!(server.projects.read().await.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_new_project() {
         let server = Risc0Server::new();
@@ -263,6 +1133,8 @@ mod tests {
             project_version: "1.0".to_string(),
             binary: compressed_binary,
             metadata: vec![],
+            replace: false,
+            signature: vec![],
         };
 
         let response = server.new_project(Request::new(request.clone())).await;
@@ -275,7 +1147,7 @@ mod tests {
         let project = projects.get(&key).unwrap();
         assert_eq!(project.project_id, "test1");
         assert_eq!(project.project_version, "1.0");
-        assert_eq!(project.elf, create_dummy_elf());
+        assert_eq!(&*project.elf, create_dummy_elf().as_slice());
         assert_eq!(
             project.image_id,
             vec![
@@ -286,105 +1158,962 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_execute_task_local_prover() {
+    async fn test_new_project_preflight() {
         let server = Risc0Server::new();
 
-        // First, add a project
-        let compressed_binary = create_compressed_binary();
-        let new_project_request = NewProjectRequest {
-            project_id: "test1".to_string(),
-            project_version: "1.0".to_string(),
-            binary: compressed_binary,
-            metadata: vec![],
-        };
-        server
-            .new_project(Request::new(new_project_request.clone()))
-            .await
-            .unwrap();
-
-        // Now, execute a task
-        let payload = serde_json::json!({
-            "private_input": "14",
-            "public_input": "3,34",
-            "receipt_type": "Stark"
+        let metadata = serde_json::json!({
+            "preflight_payloads": [{"private_input": "14", "public_input": "3,34"}]
         });
-        let execute_request = ExecuteTaskRequest {
-            project_id: "test1".to_string(),
-            project_version: "1.0".to_string(),
-            task_id: "".as_bytes().to_vec(),
-            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        let request = NewProjectRequest {
+            metadata: serde_json::to_vec(&metadata).unwrap(),
+            ..new_project_request("1.0", false)
         };
+        let response = server
+            .new_project(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.preflight_cycles > 0);
 
-        let response = server.execute_task(Request::new(execute_request)).await;
-        assert!(response.is_ok());
-
-        let result = response.unwrap().into_inner().result;
-        assert!(!result.is_empty());
-    }
-
-    // #[tokio::test]
-    // async fn test_execute_task_bonsai_prover() {
-    //     let server = Risc0Server::new();
-
-    //     // First, add a project
-    //     let compressed_binary = create_compressed_binary();
-    //     let new_project_request = NewProjectRequest {
-    //         project_id: 1,
-    //         project_version: "".to_string(),
-    //         binary: compressed_binary,
-    //         metadata: vec![],
-    //     };
-    //     server
-    //         .new_project(Request::new(new_project_request))
-    //         .await
-    //         .unwrap();
-
-    //     // Now, execute a task
-    //     let payload = serde_json::json!({
-    //         "private_input": "14",
-    //         "public_input": "3,34",
-    //         "receipt_type": "Snark"
-    //     });
-    //     let execute_request = ExecuteTaskRequest {
-    //         project_id: 1,
-    //         project_version: "".to_string(),
-    //         task_id: "".as_bytes().to_vec(),
-    //         payloads: vec![serde_json::to_vec(&payload).unwrap()],
-    //     };
-
-    //     let response = server.execute_task(Request::new(execute_request)).await;
-    //     assert!(response.is_ok());
-
-    //     let result = response.unwrap().into_inner().result;
-    //     // For Snark, we should be able to decode the result
-    //     let decoded: (Vec<u8>, Vec<u8>) = decode(
-    //         &[ethers::abi::ParamType::Bytes, ethers::abi::ParamType::Bytes],
-    //         &result,
-    //     )
-    //     .unwrap();
-    //     assert_eq!(decoded.0.len(), 192); // Groth16 seal length
-    //     assert!(!decoded.1.is_empty()); // Journal should not be empty
-    // }
+        let projects = server.projects.read().await;
+        let project = projects
+            .get(&ProjectKey::new(&"test1".to_string(), &"1.0".to_string()))
+            .unwrap();
+        assert_eq!(project.preflight_cycles, Some(response.preflight_cycles));
+    }
 
     #[tokio::test]
-    async fn test_execute_task_project_not_found() {
+    async fn test_new_project_shares_elf() {
         let server = Risc0Server::new();
+        for version in ["1.0", "1.1"] {
+            let request = NewProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: version.to_string(),
+                binary: create_compressed_binary(),
+                metadata: vec![],
+                replace: false,
+                signature: vec![],
+            };
+            server.new_project(Request::new(request)).await.unwrap();
+        }
 
-        let payload = serde_json::json!({
-            "private_input": "14",
-            "public_input": "3,34",
-            "receipt_type": "Stark"
-        });
-        let execute_request = ExecuteTaskRequest {
-            project_id: "nonexistent".to_string(),
-            project_version: "1.0".to_string(),
-            task_id: "".as_bytes().to_vec(),
-            payloads: vec![serde_json::to_vec(&payload).unwrap()],
-        };
-
-        let response = server.execute_task(Request::new(execute_request)).await;
-        assert!(response.is_err());
-        assert_eq!(response.unwrap_err().code(), tonic::Code::NotFound);
+        let projects = server.projects.read().await;
+        let v1 = projects
+            .get(&ProjectKey::new(&"test1".to_string(), &"1.0".to_string()))
+            .unwrap();
+        let v2 = projects
+            .get(&ProjectKey::new(&"test1".to_string(), &"1.1".to_string()))
+            .unwrap();
+        assert!(Arc::ptr_eq(&v1.elf, &v2.elf));
+        assert_eq!(server.images.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_new_project_replace() {
+        let server = Risc0Server::new();
+        server
+            .new_project(Request::new(new_project_request("1.0", false)))
+            .await
+            .unwrap();
+
+        let status = server
+            .new_project(Request::new(new_project_request("1.0", false)))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+        assert_eq!(
+            status.get_error_details().error_info().unwrap().reason,
+            "PROJECT_EXISTS"
+        );
+
+        server
+            .new_project(Request::new(new_project_request("1.0", true)))
+            .await
+            .unwrap();
+        assert_eq!(server.projects.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_new_project_signed_upload() {
+        use crate::core::owner::{delete_digest, upload_digest, OwnerKey};
+        use ethers::signers::{LocalWallet, Signer};
+
+        let owner: LocalWallet = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse()
+            .unwrap();
+        let server = Risc0Server::new().with_project_owners(ProjectOwners::new(HashMap::from([(
+            "test1".to_string(),
+            vec![OwnerKey::Secp256k1(owner.address())],
+        )])));
+
+        let mut request = new_project_request("1.0", false);
+        let status = server
+            .new_project(Request::new(request.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        // Signed for another version.
+        let digest = upload_digest("test1", "2.0", &request.binary, &request.metadata);
+        request.signature = owner.sign_message(digest).await.unwrap().to_vec();
+        let status = server
+            .new_project(Request::new(request.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let digest = upload_digest("test1", "1.0", &request.binary, &request.metadata);
+        request.signature = owner.sign_message(digest).await.unwrap().to_vec();
+        server.new_project(Request::new(request)).await.unwrap();
+
+        // Deletes need their own signature too.
+        let mut delete = DeleteProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            signature: vec![],
+        };
+        let status = server
+            .delete_project(Request::new(delete.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let digest = delete_digest("test1", "1.0");
+        delete.signature = owner.sign_message(digest).await.unwrap().to_vec();
+        server.delete_project(Request::new(delete)).await.unwrap();
+    }
+
+    /// Wraps `message` in a request carrying `token`, run through the
+    /// interceptor the way the server would.
+    fn with_token<T>(server: &Risc0Server, token: &str, message: T) -> Request<T> {
+        use rust_grpc::auth::AuthInterceptor;
+        use tonic::service::Interceptor;
+
+        let mut interceptor = AuthInterceptor::new(server.auth().unwrap());
+        let mut metadata = Request::new(());
+        metadata.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        let (metadata, extensions, _) = interceptor.call(metadata).unwrap().into_parts();
+        Request::from_parts(metadata, extensions, message)
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_requires_credentials() {
+        let config = AuthConfig::new(vec!["secret".to_string()], vec![]);
+        let server = Risc0Server::new().with_auth(config);
+        server
+            .new_project(with_token(
+                &server,
+                "secret",
+                new_project_request("1.0", false),
+            ))
+            .await
+            .unwrap();
+
+        let execute_request = execute_task_request("Stark");
+
+        let status = server
+            .execute_task(Request::new(execute_request.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let request = with_token(&server, "secret", execute_request);
+        let response = server.execute_task(request).await;
+        assert!(response.is_ok(), "Error: {:?}", response.err());
+    }
+
+    #[tokio::test]
+    async fn test_mutating_calls_require_token() {
+        let config = AuthConfig::new(vec!["secret".to_string()], vec![]);
+        let server = Risc0Server::new().with_auth(config);
+
+        let status = server
+            .new_project(Request::new(new_project_request("1.0", false)))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        server
+            .new_project(with_token(
+                &server,
+                "secret",
+                new_project_request("1.0", false),
+            ))
+            .await
+            .unwrap();
+
+        let convert = ConvertReceiptRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            receipt: vec![],
+            receipt_type: "Succinct".to_string(),
+        };
+        let status = server
+            .convert_receipt(Request::new(convert))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let delete = DeleteProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            signature: vec![],
+        };
+        let status = server
+            .delete_project(Request::new(delete.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        server
+            .delete_project(with_token(&server, "secret", delete))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_get_delete_project() {
+        let server = Risc0Server::new();
+        for version in ["1.1", "1.0"] {
+            server
+                .new_project(Request::new(new_project_request(version, false)))
+                .await
+                .unwrap();
+        }
+
+        let projects = server
+            .list_projects(Request::new(ListProjectsRequest {
+                project_id: "".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .projects;
+        let versions: Vec<&str> = projects
+            .iter()
+            .map(|p| p.project_version.as_str())
+            .collect();
+        assert_eq!(versions, vec!["1.0", "1.1"]);
+
+        let filtered = server
+            .list_projects(Request::new(ListProjectsRequest {
+                project_id: "other".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .projects;
+        assert!(filtered.is_empty());
+
+        let info = server
+            .get_project(Request::new(GetProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .project
+            .unwrap();
+        assert_eq!(info.elf_size, create_dummy_elf().len() as u64);
+        assert_eq!(info.metadata, br#"{"env": {"MODE": "test"}}"#.to_vec());
+        assert_eq!(info.image_id.len(), 32);
+        assert!(info.registered_at > 0);
+        assert_eq!(info.tasks_succeeded + info.tasks_failed, 0);
+
+        let delete = DeleteProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            signature: vec![],
+        };
+        server
+            .delete_project(Request::new(delete.clone()))
+            .await
+            .unwrap();
+        let status = server
+            .delete_project(Request::new(delete))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        let status = server
+            .get_project(Request::new(GetProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert_eq!(server.projects.read().await.len(), 1);
+    }
+
+    async fn resolve(server: &Risc0Server, version: &str) -> Result<String, Status> {
+        let request = GetProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: version.to_string(),
+        };
+        Ok(server
+            .get_project(Request::new(request))
+            .await?
+            .into_inner()
+            .project
+            .unwrap()
+            .project_version)
+    }
+
+    #[tokio::test]
+    async fn test_resolve_project_version() {
+        let server = Risc0Server::new();
+        for version in ["1.0", "1.2.0", "2.0.0-rc.1"] {
+            server
+                .new_project(Request::new(new_project_request(version, false)))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(resolve(&server, "1.0").await.unwrap(), "1.0");
+        assert_eq!(resolve(&server, "latest").await.unwrap(), "1.2.0");
+        assert_eq!(resolve(&server, "~1.0").await.unwrap(), "1.0");
+        assert_eq!(resolve(&server, "^2.0.0-rc").await.unwrap(), "2.0.0-rc.1");
+        let status = resolve(&server, "^3").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let status = server
+            .new_project(Request::new(new_project_request("latest", false)))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_keep_versions() {
+        let server = Risc0Server::new().with_keep_versions(2);
+        for version in ["1.1", "1.3", "1.2", "1.0"] {
+            server
+                .new_project(Request::new(new_project_request(version, false)))
+                .await
+                .unwrap();
+        }
+
+        let mut versions: Vec<String> = server
+            .projects
+            .read()
+            .await
+            .values()
+            .map(|p| p.project_version.clone())
+            .collect();
+        versions.sort();
+        // 1.0 is older than the kept versions but was just registered.
+        assert_eq!(versions, vec!["1.0", "1.2", "1.3"]);
+    }
+
+    #[tokio::test]
+    async fn test_project_task_counters() {
+        let server = Risc0Server::new();
+        server
+            .new_project(Request::new(new_project_request("1.0", false)))
+            .await
+            .unwrap();
+
+        let invalid = ExecuteTaskRequest {
+            payloads: vec![b"not json".to_vec()],
+            ..execute_task_request("Stark")
+        };
+        for execute_request in [execute_task_request("Stark"), invalid] {
+            let _ = server.execute_task(Request::new(execute_request)).await;
+        }
+
+        let info = server
+            .get_project(Request::new(GetProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .project
+            .unwrap();
+        assert_eq!(info.tasks_succeeded, 1);
+        assert_eq!(info.tasks_failed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_local_prover() {
+        let server = Risc0Server::new();
+
+        // First, add a project
+        let compressed_binary = create_compressed_binary();
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: compressed_binary,
+            metadata: vec![],
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request.clone()))
+            .await
+            .unwrap();
+
+        // Now, execute a task
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Stark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+
+        let response = server.execute_task(Request::new(execute_request)).await;
+        assert!(response.is_ok());
+
+        let response = response.unwrap().into_inner();
+        assert!(!response.result.is_empty());
+        assert!(response.stderr.is_empty());
+        assert!(!response.output_truncated);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_with_profiler() {
+        let server = Risc0Server::new();
+        server
+            .new_project(Request::new(NewProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                binary: create_compressed_binary(),
+                metadata: vec![],
+                replace: false,
+                signature: vec![],
+            }))
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Stark",
+            "enable_profiler": true
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+
+        let response = server
+            .execute_task(Request::new(execute_request))
+            .await
+            .unwrap()
+            .into_inner();
+        // The profile is a gzipped pprof protobuf.
+        assert_eq!(response.profile[..2], [0x1f, 0x8b]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_bonsai_prover() {
+        // The mock answers every Snark task with this Groth16 receipt, so the
+        // project is registered under the receipt's image ID.
+        let fixture = groth16_receipt();
+        let image_id = fixture.claim().unwrap().as_value().unwrap().pre.digest();
+        let mock = MockBonsai::start(fixture.clone()).await;
+        let server = Risc0Server::new()
+            .with_bonsai(mock.config())
+            .without_image_id_check();
+
+        // First, add a project
+        let compressed_binary = create_compressed_binary_with_id(image_id.as_words());
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: compressed_binary,
+            metadata: br#"{"journal_schema": [{"name": "message", "type": "string"}]}"#.to_vec(),
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+
+        // Now, execute a task
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Snark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+
+        let response = server.execute_task(Request::new(execute_request)).await;
+        assert!(response.is_ok(), "Error: {:?}", response.err());
+
+        let response = response.unwrap().into_inner();
+        let journal_json: Value = serde_json::from_str(&response.journal_json).unwrap();
+        assert_eq!(
+            journal_json["message"],
+            "I know your private input is greater than 11 and less than 43, and I can prove it!"
+        );
+
+        let result = response.result;
+        // For Snark, we should be able to decode the result
+        let decoded = decode(
+            &[ethers::abi::ParamType::Bytes, ethers::abi::ParamType::Bytes],
+            &result,
+        )
+        .unwrap();
+        let seal = decoded[0].clone().into_bytes().unwrap();
+        let journal = decoded[1].clone().into_bytes().unwrap();
+        assert_eq!(seal.len(), 4 + 256); // selector + Groth16 seal
+        assert_eq!(journal, fixture.journal.bytes);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_journal_schema_mismatch() {
+        let fixture = groth16_receipt();
+        let image_id = fixture.claim().unwrap().as_value().unwrap().pre.digest();
+        let mock = MockBonsai::start(fixture.clone()).await;
+        let server = Risc0Server::new()
+            .with_bonsai(mock.config())
+            .without_image_id_check();
+
+        server
+            .new_project(Request::new(NewProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                binary: create_compressed_binary_with_id(image_id.as_words()),
+                metadata: br#"{"journal_schema": [{"name": "message", "type": "u32"}]}"#.to_vec(),
+                replace: false,
+                signature: vec![],
+            }))
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Snark"
+        });
+        let response = server
+            .execute_task(Request::new(ExecuteTaskRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                task_id: "".as_bytes().to_vec(),
+                payloads: vec![serde_json::to_vec(&payload).unwrap()],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // The proof still comes back, just without the decoded journal.
+        assert!(!response.result.is_empty());
+        assert_eq!(response.journal_json, "");
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_input_binding() {
+        let fixture = groth16_receipt();
+        let image_id = fixture.claim().unwrap().as_value().unwrap().pre.digest();
+        let mock = MockBonsai::start(fixture).await;
+        let server = Risc0Server::new()
+            .with_bonsai(mock.config())
+            .without_image_id_check();
+
+        let metadata = serde_json::json!({
+            "journal_schema": [{"name": "message", "type": "string"}],
+            "input_bindings": [{"kind": "field", "payload": "claim", "journal": "message"}]
+        });
+        server
+            .new_project(Request::new(NewProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                binary: create_compressed_binary_with_id(image_id.as_words()),
+                metadata: serde_json::to_vec(&metadata).unwrap(),
+                replace: false,
+                signature: vec![],
+            }))
+            .await
+            .unwrap();
+
+        let execute = |claim: &str| {
+            let payload = serde_json::json!({
+                "private_input": "14",
+                "public_input": "3,34",
+                "receipt_type": "Snark",
+                "claim": claim
+            });
+            ExecuteTaskRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                task_id: "".as_bytes().to_vec(),
+                payloads: vec![serde_json::to_vec(&payload).unwrap()],
+            }
+        };
+
+        let matching =
+            "I know your private input is greater than 11 and less than 43, and I can prove it!";
+        let response = server.execute_task(Request::new(execute(matching))).await;
+        assert!(response.is_ok(), "Error: {:?}", response.err());
+
+        let status = server
+            .execute_task(Request::new(execute("something else")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(
+            status.get_error_details().error_info().unwrap().reason,
+            "VERIFICATION_FAILED"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_project_uploads_image_once() {
+        let fixture = groth16_receipt();
+        let image_id = fixture.claim().unwrap().as_value().unwrap().pre.digest();
+        let mock = MockBonsai::start(fixture).await;
+        let server = Risc0Server::new()
+            .with_bonsai(mock.config())
+            .without_image_id_check();
+
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: create_compressed_binary_with_id(image_id.as_words()),
+            metadata: br#"{"groth16": true}"#.to_vec(),
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+        assert_eq!(mock.image_requests(), 1);
+        assert_eq!(mock.image_uploads(), 1);
+
+        {
+            let projects = server.projects.read().await;
+            let project = projects
+                .get(&ProjectKey::new(&"test1".to_string(), &"1.0".to_string()))
+                .unwrap();
+            assert!(project.bonsai_image_id.is_some());
+        }
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Snark"
+        });
+        for _ in 0..2 {
+            let execute_request = ExecuteTaskRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                task_id: "".as_bytes().to_vec(),
+                payloads: vec![serde_json::to_vec(&payload).unwrap()],
+            };
+            let response = server.execute_task(Request::new(execute_request)).await;
+            assert!(response.is_ok(), "Error: {:?}", response.err());
+        }
+
+        // Tasks only upload their inputs.
+        assert_eq!(mock.image_requests(), 1);
+        assert_eq!(mock.sessions(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_bonsai_quota_exceeded() {
+        let mock = MockBonsai::start(groth16_receipt()).await;
+        mock.set_mode(MockMode::QuotaExceeded);
+        let server = Risc0Server::new().with_bonsai(mock.config());
+
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: create_compressed_binary(),
+            metadata: br#"{"groth16": true}"#.to_vec(),
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Snark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+
+        let response = server.execute_task(Request::new(execute_request)).await;
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err().code(), tonic::Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_bonsai_retries_transient_failure() {
+        let fixture = groth16_receipt();
+        let image_id = fixture.claim().unwrap().as_value().unwrap().pre.digest();
+        let mock = MockBonsai::start(fixture).await;
+        mock.fail_next_sessions(1);
+        let server = Risc0Server::new()
+            .with_bonsai(mock.config())
+            .without_image_id_check()
+            .with_retry_policy(RetryPolicy {
+                initial_backoff: std::time::Duration::from_millis(10),
+                ..RetryPolicy::default()
+            });
+
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: create_compressed_binary_with_id(image_id.as_words()),
+            metadata: br#"{"groth16": true}"#.to_vec(),
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Snark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+
+        let response = server.execute_task(Request::new(execute_request)).await;
+        assert!(response.is_ok(), "Error: {:?}", response.err());
+
+        let attempts = response.unwrap().into_inner().attempts;
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].attempt, 1);
+        assert_eq!(attempts[0].error_class, "Transient");
+        assert_eq!(attempts[0].backoff_ms, 10);
+        assert_eq!(mock.sessions(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_bonsai_retries_exhausted() {
+        let fixture = groth16_receipt();
+        let image_id = fixture.claim().unwrap().as_value().unwrap().pre.digest();
+        let mock = MockBonsai::start(fixture).await;
+        mock.fail_next_sessions(5);
+        let server = Risc0Server::new()
+            .with_bonsai(mock.config())
+            .without_image_id_check()
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: std::time::Duration::from_millis(10),
+                ..RetryPolicy::default()
+            });
+
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: create_compressed_binary_with_id(image_id.as_words()),
+            metadata: br#"{"groth16": true}"#.to_vec(),
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Snark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+
+        let status = server
+            .execute_task(Request::new(execute_request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        let info = status.get_error_details().error_info().unwrap().clone();
+        let attempts: serde_json::Value = serde_json::from_str(&info.metadata["attempts"]).unwrap();
+        assert_eq!(attempts.as_array().unwrap().len(), 1);
+        assert_eq!(attempts[0]["errorClass"], "Transient");
+        assert_eq!(mock.sessions(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_bonsai_not_configured() {
+        let server = Risc0Server::new();
+        let new_project_request = NewProjectRequest {
+            metadata: vec![],
+            ..new_project_request("1.0", false)
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+
+        let execute_request = execute_task_request("Snark");
+        let response = server.execute_task(Request::new(execute_request)).await;
+        assert!(response.is_err());
+        assert_eq!(
+            response.unwrap_err().code(),
+            tonic::Code::FailedPrecondition
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_rejected_while_draining() {
+        let server = Risc0Server::new();
+        server.drain().start_draining();
+
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![],
+        };
+
+        let err = server
+            .execute_task(Request::new(execute_request))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unavailable);
+        assert_eq!(server.drain().in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_project_not_found() {
+        let server = Risc0Server::new();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Stark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "nonexistent".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+
+        let response = server.execute_task(Request::new(execute_request)).await;
+        assert!(response.is_err());
+        let status = response.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        let details = status.get_error_details();
+        assert_eq!(details.error_info().unwrap().reason, "UNKNOWN_PROJECT");
+        assert_eq!(
+            details.resource_info().unwrap().resource_name,
+            "nonexistent@1.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_invalid_payload() {
+        let server = Risc0Server::new();
+        server
+            .new_project(Request::new(NewProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                binary: create_compressed_binary(),
+                metadata: vec![],
+                replace: false,
+                signature: vec![],
+            }))
+            .await
+            .unwrap();
+
+        for payloads in [
+            vec![b"not json".to_vec()],
+            vec![b"{}".to_vec(), vec![0xff, 0xfe]],
+        ] {
+            let execute_request = ExecuteTaskRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                task_id: "".as_bytes().to_vec(),
+                payloads,
+            };
+            let status = server
+                .execute_task(Request::new(execute_request))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+            let details = status.get_error_details();
+            assert_eq!(
+                details.bad_request().unwrap().field_violations[0].field,
+                "payloads"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_project_invalid_binary() {
+        let server = Risc0Server::new();
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0xff, 0xfe, 0xfd]).unwrap();
+        let binaries = [
+            b"not zlib".to_vec(),
+            encoder.finish().unwrap(),
+            create_compressed_binary_with_id(&[]),
+        ];
+        for binary in binaries {
+            let request = NewProjectRequest {
+                project_id: "test1".to_string(),
+                project_version: "1.0".to_string(),
+                binary,
+                metadata: vec![],
+                replace: false,
+                signature: vec![],
+            };
+            let status = server.new_project(Request::new(request)).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_project_image_id_mismatch() {
+        let server = Risc0Server::new();
+
+        let request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: create_compressed_binary_with_id(&[1; 8]),
+            metadata: vec![],
+            replace: false,
+            signature: vec![],
+        };
+        let status = server.new_project(Request::new(request)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("does not match"));
+        assert!(server.images.is_empty());
     }
 
     #[tokio::test]
@@ -403,6 +2132,170 @@ mod tests {
         assert_eq!(response.unwrap_err().code(), tonic::Code::InvalidArgument);
     }
 
+    #[tokio::test]
+    async fn test_convert_receipt_composite_to_succinct() {
+        let server = Risc0Server::new();
+
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: create_compressed_binary(),
+            metadata: vec![],
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Stark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+        let composite = server
+            .execute_task(Request::new(execute_request))
+            .await
+            .unwrap()
+            .into_inner()
+            .result;
+
+        let convert_request = ConvertReceiptRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            receipt: composite,
+            receipt_type: "Succinct".to_string(),
+        };
+        let response = server.convert_receipt(Request::new(convert_request)).await;
+        assert!(response.is_ok(), "Error: {:?}", response.err());
+
+        let receipt: Receipt =
+            serde_json::from_slice(&response.unwrap().into_inner().result).unwrap();
+        assert_eq!(ReceiptKind::of(&receipt), ReceiptKind::Succinct);
+    }
+
+    #[tokio::test]
+    async fn test_verify_receipt() {
+        let server = Risc0Server::new();
+
+        let new_project_request = NewProjectRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            binary: create_compressed_binary(),
+            metadata: vec![],
+            replace: false,
+            signature: vec![],
+        };
+        server
+            .new_project(Request::new(new_project_request))
+            .await
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "private_input": "14",
+            "public_input": "3,34",
+            "receipt_type": "Stark"
+        });
+        let execute_request = ExecuteTaskRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            task_id: "".as_bytes().to_vec(),
+            payloads: vec![serde_json::to_vec(&payload).unwrap()],
+        };
+        let receipt = server
+            .execute_task(Request::new(execute_request))
+            .await
+            .unwrap()
+            .into_inner()
+            .result;
+
+        let verify_request = VerifyReceiptRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            image_id: vec![],
+            receipt: receipt.clone(),
+        };
+        let response = server
+            .verify_receipt(Request::new(verify_request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.valid, "Error: {}", response.error);
+        assert_eq!(response.receipt_type, "Composite");
+        assert!(!response.journal.is_empty());
+
+        // A receipt checked against another image ID must be rejected.
+        let verify_request = VerifyReceiptRequest {
+            project_id: "".to_string(),
+            project_version: "".to_string(),
+            image_id: vec![0u8; 32],
+            receipt,
+        };
+        let response = server
+            .verify_receipt(Request::new(verify_request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.valid);
+        assert!(!response.error.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_verify_receipt_missing_image_id() {
+        let server = Risc0Server::new();
+
+        let verify_request = VerifyReceiptRequest {
+            project_id: "".to_string(),
+            project_version: "".to_string(),
+            image_id: vec![],
+            receipt: vec![],
+        };
+        let response = server.verify_receipt(Request::new(verify_request)).await;
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_convert_receipt_groth16_unsupported() {
+        let server = Risc0Server::new();
+
+        let convert_request = ConvertReceiptRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            receipt: vec![],
+            receipt_type: "Groth16".to_string(),
+        };
+        let status = server
+            .convert_receipt(Request::new(convert_request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        let details = status.get_error_details();
+        assert_eq!(details.error_info().unwrap().reason, "UNSUPPORTED");
+    }
+
+    #[tokio::test]
+    async fn test_convert_receipt_invalid_type() {
+        let server = Risc0Server::new();
+
+        let convert_request = ConvertReceiptRequest {
+            project_id: "test1".to_string(),
+            project_version: "1.0".to_string(),
+            receipt: vec![],
+            receipt_type: "Snark".to_string(),
+        };
+        let response = server.convert_receipt(Request::new(convert_request)).await;
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
     #[test]
     fn test_extract_data() {
         let server = Risc0Server::new();
@@ -416,5 +2309,8 @@ mod tests {
 
         let id_data = server.extract_data::<u32>(&IMAGE_ID_RE, content);
         assert_eq!(id_data, Some(vec![10, 20, 30]));
+
+        let bad = "pub const TEST_ID: [u32; 2] = [10, x];";
+        assert_eq!(server.extract_data::<u32>(&IMAGE_ID_RE, bad), None);
     }
 }
@@ -1,26 +1,128 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use config::Config;
 use grpc::server::Risc0Server;
+use rust_grpc::auth::AuthInterceptor;
 use rust_grpc::grpc::vm::vm_server::VmServer;
+use rust_grpc::grpc::FILE_DESCRIPTOR_SET;
+use rust_grpc::tls::ReloadableTls;
 
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
 use tonic::transport::Server;
+use tonic_health::ServingStatus;
 
+pub mod config;
 mod core;
 mod grpc;
+mod metrics;
+mod shutdown;
+mod telemetry;
+#[cfg(test)]
+mod tests;
 
-pub async fn start_grpc_server(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let addr = addr.parse()?;
-    let risc0_server = Risc0Server::new();
+pub async fn start_grpc_server(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let addr: std::net::SocketAddr = config.listen.parse()?;
+    let risc0_server = Risc0Server::from_config(&config)?;
 
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::DEBUG)
-        .init();
+    telemetry::init(&config.log, config.tracing.as_ref())?;
 
     tracing::info!(message = "Starting server.", %addr);
 
-    Server::builder()
-        .trace_fn(|_| tracing::info_span!("risc0_server"))
-        .add_service(VmServer::new(risc0_server))
-        .serve(addr)
-        .await?;
+    if let Some(metrics) = &config.metrics {
+        let metrics_addr: std::net::SocketAddr = metrics.listen.parse()?;
+        tracing::info!(%metrics_addr, "Serving metrics");
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(metrics_addr).await {
+                tracing::error!("Metrics endpoint failed: {}", e);
+            }
+        });
+    }
+
+    // vm.VM and the overall status stay NOT_SERVING until the first backend
+    // check passes.
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    for service in grpc::health::SERVICES {
+        health_reporter
+            .set_service_status(service, ServingStatus::NotServing)
+            .await;
+    }
+    let drain = risc0_server.drain();
+    let watcher = tokio::spawn(grpc::health::watch_backends(
+        health_reporter.clone(),
+        risc0_server.bonsai(),
+    ));
+
+    // On SIGTERM, refuse new tasks and report NOT_SERVING so load balancers
+    // move away, then give in-flight proofs the grace period to finish.
+    let grace_period = Duration::from_secs(config.shutdown.grace_period_secs);
+    let expired = Arc::new(Notify::new());
+    let on_signal = {
+        let expired = expired.clone();
+        async move {
+            shutdown::signal().await;
+            tracing::info!(in_flight = drain.in_flight(), "Shutting down");
+            drain.start_draining();
+            // Stop the watcher first so a backend check finishing now cannot
+            // flip the status back to SERVING.
+            watcher.abort();
+            let _ = watcher.await;
+            for service in grpc::health::SERVICES {
+                health_reporter
+                    .set_service_status(service, ServingStatus::NotServing)
+                    .await;
+            }
+            if tokio::time::timeout(grace_period, drain.wait_idle())
+                .await
+                .is_err()
+            {
+                // Bonsai tasks can resume from their saved session; local
+                // proofs are lost.
+                tracing::warn!(
+                    in_flight = drain.in_flight(),
+                    "Grace period expired, abandoning in-flight tasks; local proofs are lost"
+                );
+                expired.notify_one();
+            }
+        }
+    };
+
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()?;
+
+    let mut builder = Server::builder().trace_fn(telemetry::request_span);
+    let router = builder.add_service(health_service).add_service(reflection);
+    let router = match risc0_server.auth() {
+        Some(auth) => router.add_service(VmServer::with_interceptor(
+            risc0_server,
+            AuthInterceptor::new(auth),
+        )),
+        None => router.add_service(VmServer::new(risc0_server)),
+    };
+    let serve = async {
+        match config.tls {
+            Some(config) => {
+                tracing::info!(mutual = config.client_ca_path.is_some(), "TLS enabled");
+                let tls = ReloadableTls::new(config)?;
+                tls.reload_on_sighup()?;
+                let listener = TcpListener::bind(addr).await?;
+                router
+                    .serve_with_incoming_shutdown(tls.incoming(listener), on_signal)
+                    .await?;
+            }
+            None => router.serve_with_shutdown(addr, on_signal).await?,
+        }
+        Ok::<_, Box<dyn std::error::Error>>(())
+    };
+    tokio::select! {
+        res = serve => res?,
+        // Connections still open past the grace period are dropped.
+        _ = expired.notified() => {}
+    }
 
+    telemetry::shutdown();
     Ok(())
 }
@@ -1,9 +1,52 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+use risc0_server::config::{Config, LogFormat};
 use risc0_server::start_grpc_server;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // start grpc server
-    start_grpc_server("0.0.0.0:4001").await?;
+#[derive(Parser)]
+#[command(version, about = "RISC Zero VM gRPC server")]
+struct Cli {
+    /// TOML config file; environment variables override its values.
+    #[arg(short, long, env = "RISC0_SERVER_CONFIG")]
+    config: Option<PathBuf>,
+    /// Address to listen on.
+    #[arg(long, env = "LISTEN_ADDR")]
+    listen: Option<String>,
+    /// Log filter, e.g. `info` or `risc0_server=debug`.
+    #[arg(long, env = "LOG_LEVEL")]
+    log_level: Option<String>,
+    #[arg(long, env = "LOG_FORMAT", value_enum)]
+    log_format: Option<LogFormat>,
+    /// Print the effective config, with secrets redacted, and exit.
+    #[arg(long)]
+    print_config: bool,
+}
 
-    Ok(())
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Some(listen) = cli.listen {
+        config.listen = listen;
+    }
+    if let Some(level) = cli.log_level {
+        config.log.level = level;
+    }
+    if let Some(format) = cli.log_format {
+        config.log.format = format;
+    }
+
+    println!("effective config:\n{}", config.display());
+    if cli.print_config {
+        return Ok(());
+    }
+
+    // start grpc server
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(start_grpc_server(config));
+    // The grace period is over by now. Proofs still running on blocking
+    // threads would otherwise keep the process alive until they finish.
+    runtime.shutdown_timeout(Duration::ZERO);
+    result
 }
@@ -0,0 +1,142 @@
+//! Prometheus metrics, served as text on `/metrics` when `[metrics]` is
+//! configured.
+
+use std::net::SocketAddr;
+
+use axum::{routing::get, Router};
+use lazy_static::lazy_static;
+use prometheus::{
+    exponential_buckets, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use tokio::net::TcpListener;
+use tonic::Status;
+use tonic_types::StatusExt;
+
+lazy_static! {
+    pub static ref TASKS: IntCounterVec = register_int_counter_vec!(
+        "risc0_tasks_total",
+        "Finished tasks, by project and outcome (ok or the error reason).",
+        &["project_id", "project_version", "outcome"]
+    )
+    .unwrap();
+    pub static ref PROOF_DURATION: HistogramVec = register_histogram_vec!(
+        "risc0_proof_duration_seconds",
+        "Time spent proving a task, retries included.",
+        &["project_id", "backend"],
+        exponential_buckets(0.5, 2.0, 16).unwrap()
+    )
+    .unwrap();
+    pub static ref TASK_CYCLES: HistogramVec = register_histogram_vec!(
+        "risc0_task_cycles",
+        "Guest cycles per locally proven task.",
+        &["project_id"],
+        exponential_buckets(1024.0, 4.0, 10).unwrap()
+    )
+    .unwrap();
+    pub static ref TASK_QUEUE_DEPTH: IntGaugeVec = register_int_gauge_vec!(
+        "risc0_task_queue_depth",
+        "Tasks accepted and not yet finished.",
+        &["project_id"]
+    )
+    .unwrap();
+    pub static ref PROJECTS: IntGauge =
+        register_int_gauge!("risc0_projects", "Registered project versions.").unwrap();
+    pub static ref IMAGE_CACHE: IntCounterVec = register_int_counter_vec!(
+        "risc0_image_cache_total",
+        "ELF image store lookups, by project and hit or miss.",
+        &["project_id", "result"]
+    )
+    .unwrap();
+    pub static ref BONSAI_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "risc0_bonsai_request_duration_seconds",
+        "Latency of Bonsai API calls, by project, operation and outcome.",
+        &["project_id", "operation", "outcome"]
+    )
+    .unwrap();
+}
+
+/// Holds a task in the queue depth gauge until dropped.
+pub struct Queued(IntGauge);
+
+impl Queued {
+    pub fn new(project_id: &str) -> Self {
+        let gauge = TASK_QUEUE_DEPTH.with_label_values(&[project_id]);
+        gauge.inc();
+        Queued(gauge)
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+pub fn record_task<T>(project_id: &str, version: &str, result: &Result<T, Status>) {
+    TASKS
+        .with_label_values(&[project_id, version, &outcome(result)])
+        .inc();
+}
+
+/// `ok`, or the `ErrorInfo` reason of a failed call.
+fn outcome<T>(result: &Result<T, Status>) -> String {
+    match result {
+        Ok(_) => "ok".to_string(),
+        Err(status) => match status.get_error_details().error_info() {
+            Some(info) => info.reason.clone(),
+            None => format!("{:?}", status.code()),
+        },
+    }
+}
+
+pub fn render() -> String {
+    TextEncoder::new()
+        .encode_to_string(&prometheus::gather())
+        .unwrap_or_else(|e| format!("# failed to encode metrics: {}\n", e))
+}
+
+pub async fn serve(addr: SocketAddr) -> std::io::Result<()> {
+    let app = Router::new().route("/metrics", get(|| async { render() }));
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::error::ServerError;
+
+    #[test]
+    fn test_record_task() {
+        let failed: Result<(), Status> = Err(ServerError::GuestPanic("boom".to_string()).into());
+        record_task("metrics-test", "1.0", &Ok::<_, Status>(()));
+        record_task("metrics-test", "1.0", &failed);
+        record_task(
+            "metrics-test",
+            "1.0",
+            &Err::<(), _>(Status::internal("plain")),
+        );
+
+        let text = render();
+        for outcome in ["ok", "GUEST_PANIC", "Internal"] {
+            let line = format!(
+                "risc0_tasks_total{{outcome=\"{}\",project_id=\"metrics-test\",project_version=\"1.0\"}} 1",
+                outcome
+            );
+            assert!(text.contains(&line), "missing {} in\n{}", line, text);
+        }
+
+        {
+            let _queued = Queued::new("metrics-test");
+            assert_eq!(
+                TASK_QUEUE_DEPTH.with_label_values(&["metrics-test"]).get(),
+                1
+            );
+        }
+        assert_eq!(
+            TASK_QUEUE_DEPTH.with_label_values(&["metrics-test"]).get(),
+            0
+        );
+    }
+}
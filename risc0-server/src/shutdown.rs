@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// Tracks in-flight tasks so a shutdown can stop taking new ones and wait for
+/// the rest to finish.
+#[derive(Default)]
+pub struct Drain {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+/// Held for the duration of one task.
+pub struct TaskGuard(Arc<Drain>);
+
+impl Drain {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Drain::default())
+    }
+
+    /// Registers a task, or returns `None` once draining has started.
+    pub fn start_task(self: &Arc<Self>) -> Option<TaskGuard> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        if self.draining.load(Ordering::SeqCst) {
+            self.finish_task();
+            return None;
+        }
+        Some(TaskGuard(self.clone()))
+    }
+
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once draining has started and no task is left.
+    pub async fn wait_idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.is_draining() && self.in_flight() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    fn finish_task(&self) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.finish_task();
+    }
+}
+
+/// Resolves on SIGTERM or Ctrl-C.
+pub async fn signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = term.recv() => {}
+                    _ = ctrl_c => {}
+                }
+            }
+            Err(_) => {
+                let _ = ctrl_c.await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = ctrl_c.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_drain() {
+        let drain = Drain::new();
+        let task = drain.start_task().unwrap();
+        assert_eq!(drain.in_flight(), 1);
+
+        drain.start_draining();
+        assert!(drain.start_task().is_none());
+        assert_eq!(drain.in_flight(), 1);
+
+        let waiter = tokio::spawn({
+            let drain = drain.clone();
+            async move { drain.wait_idle().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(task);
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,295 @@
+//! In-process mock of the Bonsai REST API, enough for `BonsaiClient` to run a
+//! full Groth16 flow offline: image upload, input upload, session create,
+//! status polling, SNARK conversion and receipt download, plus the version
+//! endpoint health checks ping.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use risc0_zkvm::Receipt;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::core::bonsai::BonsaiConfig;
+
+pub const MOCK_API_KEY: &str = "mock-key";
+
+/// Number of status polls a session or snark stays RUNNING before it succeeds.
+const RUNNING_POLLS: u64 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MockMode {
+    Normal,
+    /// Session creation is rejected the way Bonsai rejects over-quota users.
+    QuotaExceeded,
+    /// Sessions end in FAILED.
+    SessionFails,
+    /// Sessions never leave RUNNING.
+    Stuck,
+}
+
+pub fn groth16_receipt() -> Receipt {
+    serde_json::from_str(include_str!("groth16_receipt.json")).unwrap()
+}
+
+struct MockState {
+    url: String,
+    receipt: Vec<u8>,
+    mode: Mutex<MockMode>,
+    next_id: AtomicU64,
+    images: Mutex<HashSet<String>>,
+    image_requests: AtomicU64,
+    image_uploads: AtomicU64,
+    unavailable_sessions: AtomicU64,
+    inputs: Mutex<HashSet<String>>,
+    sessions: Mutex<HashMap<String, u64>>,
+    snarks: Mutex<HashMap<String, u64>>,
+}
+
+impl MockState {
+    fn next_id(&self) -> String {
+        format!("mock-{}", self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+pub struct MockBonsai {
+    state: Arc<MockState>,
+}
+
+impl MockBonsai {
+    /// Serves `receipt` as the result of every session and snark.
+    pub async fn start(receipt: Receipt) -> MockBonsai {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let state = Arc::new(MockState {
+            url,
+            receipt: bincode::serialize(&receipt).unwrap(),
+            mode: Mutex::new(MockMode::Normal),
+            next_id: AtomicU64::new(0),
+            images: Mutex::new(HashSet::new()),
+            image_requests: AtomicU64::new(0),
+            image_uploads: AtomicU64::new(0),
+            unavailable_sessions: AtomicU64::new(0),
+            inputs: Mutex::new(HashSet::new()),
+            sessions: Mutex::new(HashMap::new()),
+            snarks: Mutex::new(HashMap::new()),
+        });
+
+        let app = Router::new()
+            .route("/version", get(version))
+            .route("/images/upload/:image_id", get(image_upload_url))
+            .route("/inputs/upload", get(input_upload_url))
+            .route("/put/images/:image_id", put(put_image))
+            .route("/put/inputs/:input_id", put(put_input))
+            .route("/sessions/create", post(create_session))
+            .route("/sessions/status/:uuid", get(session_status))
+            .route("/snark/create", post(create_snark))
+            .route("/snark/status/:uuid", get(snark_status))
+            .route("/download/:uuid", get(download))
+            .with_state(state.clone());
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        MockBonsai { state }
+    }
+
+    pub fn config(&self) -> BonsaiConfig {
+        let mut config = BonsaiConfig::new(&self.state.url, MOCK_API_KEY);
+        config.poll_interval = std::time::Duration::from_millis(10);
+        config
+    }
+
+    pub fn set_mode(&self, mode: MockMode) {
+        *self.state.mode.lock().unwrap() = mode;
+    }
+
+    /// Rejects the next `n` session creations with 503, as during an outage.
+    pub fn fail_next_sessions(&self, n: u64) {
+        self.state.unavailable_sessions.store(n, Ordering::Relaxed);
+    }
+
+    /// Number of image upload URL requests, including ones for known images.
+    pub fn image_requests(&self) -> u64 {
+        self.state.image_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn image_uploads(&self) -> u64 {
+        self.state.image_uploads.load(Ordering::Relaxed)
+    }
+
+    pub fn sessions(&self) -> usize {
+        self.state.sessions.lock().unwrap().len()
+    }
+}
+
+fn authorized(headers: &HeaderMap) -> Result<(), Response> {
+    match headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        Some(MOCK_API_KEY) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "invalid api key").into_response()),
+    }
+}
+
+async fn version(headers: HeaderMap) -> Response {
+    if let Err(res) = authorized(&headers) {
+        return res;
+    }
+    Json(json!({ "risc0_zkvm": [risc0_zkvm::VERSION] })).into_response()
+}
+
+async fn image_upload_url(
+    State(state): State<Arc<MockState>>,
+    Path(image_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(res) = authorized(&headers) {
+        return res;
+    }
+    state.image_requests.fetch_add(1, Ordering::Relaxed);
+    if state.images.lock().unwrap().contains(&image_id) {
+        return StatusCode::NO_CONTENT.into_response();
+    }
+    Json(json!({ "url": format!("{}/put/images/{}", state.url, image_id) })).into_response()
+}
+
+async fn input_upload_url(State(state): State<Arc<MockState>>, headers: HeaderMap) -> Response {
+    if let Err(res) = authorized(&headers) {
+        return res;
+    }
+    let uuid = state.next_id();
+    Json(json!({ "url": format!("{}/put/inputs/{}", state.url, uuid), "uuid": uuid }))
+        .into_response()
+}
+
+async fn put_image(State(state): State<Arc<MockState>>, Path(image_id): Path<String>) {
+    state.image_uploads.fetch_add(1, Ordering::Relaxed);
+    state.images.lock().unwrap().insert(image_id);
+}
+
+async fn put_input(
+    State(state): State<Arc<MockState>>,
+    Path(input_id): Path<String>,
+    _body: Bytes,
+) {
+    state.inputs.lock().unwrap().insert(input_id);
+}
+
+async fn create_session(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Json(req): Json<Value>,
+) -> Response {
+    if let Err(res) = authorized(&headers) {
+        return res;
+    }
+    if *state.mode.lock().unwrap() == MockMode::QuotaExceeded {
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            "cycle budget exhausted for this api key",
+        )
+            .into_response();
+    }
+    let unavailable = state
+        .unavailable_sessions
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        .is_ok();
+    if unavailable {
+        return (StatusCode::SERVICE_UNAVAILABLE, "service unavailable").into_response();
+    }
+    let img = req["img"].as_str().unwrap_or_default();
+    let input = req["input"].as_str().unwrap_or_default();
+    if !state.images.lock().unwrap().contains(img) {
+        return (StatusCode::BAD_REQUEST, "unknown image").into_response();
+    }
+    if !state.inputs.lock().unwrap().contains(input) {
+        return (StatusCode::BAD_REQUEST, "unknown input").into_response();
+    }
+
+    let uuid = state.next_id();
+    state.sessions.lock().unwrap().insert(uuid.clone(), 0);
+    Json(json!({ "uuid": uuid })).into_response()
+}
+
+async fn session_status(
+    State(state): State<Arc<MockState>>,
+    Path(uuid): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(res) = authorized(&headers) {
+        return res;
+    }
+    let mut sessions = state.sessions.lock().unwrap();
+    let Some(polls) = sessions.get_mut(&uuid) else {
+        return (StatusCode::NOT_FOUND, "unknown session").into_response();
+    };
+    *polls += 1;
+    let mode = *state.mode.lock().unwrap();
+    if mode == MockMode::SessionFails {
+        return Json(json!({ "status": "FAILED", "error_msg": "Guest panicked: mock failure" }))
+            .into_response();
+    }
+    if *polls <= RUNNING_POLLS || mode == MockMode::Stuck {
+        return Json(json!({ "status": "RUNNING", "state": "ProveSegments: 1/1" })).into_response();
+    }
+    Json(json!({
+        "status": "SUCCEEDED",
+        "receipt_url": format!("{}/download/{}", state.url, uuid),
+    }))
+    .into_response()
+}
+
+async fn create_snark(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Json(req): Json<Value>,
+) -> Response {
+    if let Err(res) = authorized(&headers) {
+        return res;
+    }
+    let session_id = req["session_id"].as_str().unwrap_or_default();
+    if !state.sessions.lock().unwrap().contains_key(session_id) {
+        return (StatusCode::BAD_REQUEST, "unknown session").into_response();
+    }
+
+    let uuid = state.next_id();
+    state.snarks.lock().unwrap().insert(uuid.clone(), 0);
+    Json(json!({ "uuid": uuid })).into_response()
+}
+
+async fn snark_status(
+    State(state): State<Arc<MockState>>,
+    Path(uuid): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(res) = authorized(&headers) {
+        return res;
+    }
+    let mut snarks = state.snarks.lock().unwrap();
+    let Some(polls) = snarks.get_mut(&uuid) else {
+        return (StatusCode::NOT_FOUND, "unknown snark").into_response();
+    };
+    *polls += 1;
+    if *polls <= RUNNING_POLLS {
+        return Json(json!({ "status": "RUNNING" })).into_response();
+    }
+    Json(json!({
+        "status": "SUCCEEDED",
+        "output": format!("{}/download/{}", state.url, uuid),
+    }))
+    .into_response()
+}
+
+async fn download(State(state): State<Arc<MockState>>) -> Vec<u8> {
+    state.receipt.clone()
+}
@@ -0,0 +1 @@
+pub mod bonsai_mock;
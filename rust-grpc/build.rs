@@ -2,15 +2,20 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let proto_file = "../proto/vm_runtime.proto";
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
-    tonic_build::configure()
-        .build_client(true)
-        .build_server(true)
-        .file_descriptor_set_path(out_dir.join("runtime_descriptor.bin"))
-        .out_dir("./src/grpc")
-        .compile_protos(&[proto_file], &["../proto"])?;
+    // One descriptor set per proto, so each server reflects only its own API.
+    for (proto_file, descriptor) in [
+        ("../proto/vm_runtime.proto", "runtime_descriptor.bin"),
+        ("../proto/wasm_runtime.proto", "wasm_runtime_descriptor.bin"),
+    ] {
+        tonic_build::configure()
+            .build_client(true)
+            .build_server(true)
+            .file_descriptor_set_path(out_dir.join(descriptor))
+            .out_dir("./src/grpc")
+            .compile_protos(&[proto_file], &["../proto"])?;
+    }
 
     Ok(())
 }
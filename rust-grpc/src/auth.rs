@@ -0,0 +1,428 @@
+//! Caller authentication shared by the VM servers.
+//!
+//! A caller proves itself either with a bearer token from config or with a
+//! sequencer signature over the task. Tokens are checked by
+//! [`AuthInterceptor`]; signatures cover the request body, which interceptors
+//! cannot see, so handlers finish the check with [`AuthConfig::authorize_task`].
+//! Calls with no task to sign, such as registering a project, take only a
+//! token, checked with [`AuthConfig::require_token`].
+
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Metadata key carrying a hex sequencer signature for RPCs whose request has
+/// no signature field.
+pub const SIGNATURE_HEADER: &str = "x-sequencer-signature";
+
+pub type Address = [u8; 20];
+
+#[derive(Debug)]
+pub enum AuthError {
+    /// The request carried no credentials.
+    Missing,
+    /// Credentials, or auth settings, that failed a check.
+    Invalid(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Missing => f.write_str("missing credentials"),
+            AuthError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl From<AuthError> for Status {
+    fn from(err: AuthError) -> Self {
+        match err {
+            AuthError::Missing => Status::unauthenticated(err.to_string()),
+            AuthError::Invalid(_) => Status::permission_denied(err.to_string()),
+        }
+    }
+}
+
+/// What a request authenticated with, left in its extensions by
+/// [`AuthInterceptor`].
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    Token,
+    Signature(Vec<u8>),
+}
+
+/// The `[auth]` section of a server config file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub tokens: Vec<String>,
+    /// Hex addresses of sequencers allowed to sign tasks.
+    pub sequencers: Vec<String>,
+}
+
+impl AuthSettings {
+    /// Replaces either list with comma-separated `AUTH_TOKENS` or
+    /// `AUTH_SEQUENCERS` when set.
+    pub fn apply_env(&mut self) {
+        let list = |name: &str| -> Option<Vec<String>> {
+            let value = env::var(name).ok()?;
+            Some(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            )
+        };
+        if let Some(tokens) = list("AUTH_TOKENS") {
+            self.tokens = tokens;
+        }
+        if let Some(sequencers) = list("AUTH_SEQUENCERS") {
+            self.sequencers = sequencers;
+        }
+    }
+
+    /// A copy safe to log, with tokens masked.
+    pub fn redacted(&self) -> Self {
+        AuthSettings {
+            tokens: vec!["<redacted>".to_string(); self.tokens.len()],
+            sequencers: self.sequencers.clone(),
+        }
+    }
+
+    /// Returns `None` when no tokens or sequencers are configured, leaving the
+    /// server open.
+    pub fn build(&self) -> Result<Option<AuthConfig>, AuthError> {
+        if self.tokens.is_empty() && self.sequencers.is_empty() {
+            return Ok(None);
+        }
+        let sequencers = self
+            .sequencers
+            .iter()
+            .map(|s| parse_address(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(AuthConfig::new(self.tokens.clone(), sequencers)))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    tokens: Vec<String>,
+    sequencers: Vec<Address>,
+}
+
+impl AuthConfig {
+    pub fn new(tokens: Vec<String>, sequencers: Vec<Address>) -> Self {
+        AuthConfig { tokens, sequencers }
+    }
+
+    /// Reads comma-separated `AUTH_TOKENS` and `AUTH_SEQUENCERS` (hex
+    /// addresses). Returns `None` when neither is set, leaving the server open.
+    pub fn from_env() -> Result<Option<Self>, AuthError> {
+        let mut settings = AuthSettings::default();
+        settings.apply_env();
+        settings.build()
+    }
+
+    pub fn check_token(&self, token: &str) -> bool {
+        self.tokens
+            .iter()
+            .any(|t| constant_time_eq(t.as_bytes(), token.as_bytes()))
+    }
+
+    /// Checks `signature` is an EIP-191 signature of `digest` by an allowed
+    /// sequencer.
+    pub fn verify_sequencer(&self, digest: &[u8; 32], signature: &[u8]) -> Result<(), AuthError> {
+        let signer = recover(digest, signature)?;
+        if !self.sequencers.contains(&signer) {
+            return Err(AuthError::Invalid(format!(
+                "0x{} is not an allowed sequencer",
+                hex::encode(signer)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Finishes authenticating a task request, verifying a signature against
+    /// the task's [`task_digest`]. `body_signature` is the request's own
+    /// signature field, if it has one.
+    pub fn authorize_task<T>(
+        &self,
+        request: &Request<T>,
+        body_signature: &[u8],
+        digest: impl FnOnce() -> [u8; 32],
+    ) -> Result<(), AuthError> {
+        let signature = match request.extensions().get::<Credentials>() {
+            Some(Credentials::Token) => return Ok(()),
+            Some(Credentials::Signature(signature)) => signature.as_slice(),
+            None if !body_signature.is_empty() => body_signature,
+            None => return Err(AuthError::Missing),
+        };
+        self.verify_sequencer(&digest(), signature)
+    }
+
+    /// Authenticates a call that has no task for a sequencer to sign, such as
+    /// registering or deleting a project: only a bearer token is accepted.
+    pub fn require_token<T>(&self, request: &Request<T>) -> Result<(), AuthError> {
+        match request.extensions().get::<Credentials>() {
+            Some(Credentials::Token) => Ok(()),
+            Some(Credentials::Signature(_)) => Err(AuthError::Invalid(
+                "sequencer signatures only cover tasks; use a bearer token".to_string(),
+            )),
+            None => Err(AuthError::Missing),
+        }
+    }
+}
+
+/// The 32 bytes a sequencer signs for a task: keccak256 over the
+/// length-prefixed project ID, version and task ID, followed by the SHA-256 of
+/// the length-prefixed payloads.
+pub fn task_digest(
+    project_id: &str,
+    version: &str,
+    task_id: &[u8],
+    payloads: &[&[u8]],
+) -> [u8; 32] {
+    let mut payload_hash = Sha256::new();
+    for payload in payloads {
+        payload_hash.update((payload.len() as u64).to_le_bytes());
+        payload_hash.update(payload);
+    }
+
+    let mut hasher = Keccak256::new();
+    for field in [project_id.as_bytes(), version.as_bytes(), task_id] {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field);
+    }
+    hasher.update(payload_hash.finalize());
+    hasher.finalize().into()
+}
+
+fn recover(digest: &[u8; 32], signature: &[u8]) -> Result<Address, AuthError> {
+    if signature.len() != 65 {
+        return Err(AuthError::Invalid(format!(
+            "signature must be 65 bytes, got {}",
+            signature.len()
+        )));
+    }
+    let sig = Signature::from_slice(&signature[..64])
+        .map_err(|e| AuthError::Invalid(format!("malformed signature: {}", e)))?;
+    // Accept both raw (0/1) and Ethereum (27/28) recovery ids.
+    let v = signature[64];
+    let recid = RecoveryId::from_byte(if v >= 27 { v - 27 } else { v })
+        .ok_or_else(|| AuthError::Invalid(format!("invalid recovery id {}", v)))?;
+
+    let prehash = eip191_hash(digest);
+    let key = VerifyingKey::recover_from_prehash(&prehash, &sig, recid)
+        .map_err(|e| AuthError::Invalid(format!("Failed to recover signer: {}", e)))?;
+    Ok(address(&key))
+}
+
+fn eip191_hash(digest: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"\x19Ethereum Signed Message:\n32");
+    hasher.update(digest);
+    hasher.finalize().into()
+}
+
+fn address(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+fn parse_address(s: &str) -> Result<Address, AuthError> {
+    let bytes = hex::decode(s.trim_start_matches("0x"))
+        .map_err(|e| AuthError::Invalid(format!("invalid sequencer address {}: {}", s, e)))?;
+    bytes
+        .try_into()
+        .map_err(|_| AuthError::Invalid(format!("sequencer address {} must be 20 bytes", s)))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Validates bearer tokens and picks up [`SIGNATURE_HEADER`], recording the
+/// result as [`Credentials`]. Requests with neither pass through, since a task
+/// may carry its signature in the body; every handler that changes state or
+/// proves must then reject them with [`AuthConfig::authorize_task`] or
+/// [`AuthConfig::require_token`].
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    config: Arc<AuthConfig>,
+}
+
+impl AuthInterceptor {
+    pub fn new(config: Arc<AuthConfig>) -> Self {
+        AuthInterceptor { config }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let metadata = request.metadata();
+        let credentials = if let Some(value) = metadata.get("authorization") {
+            let token = value
+                .to_str()
+                .ok()
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .ok_or_else(|| Status::unauthenticated("authorization must be a bearer token"))?;
+            if !self.config.check_token(token.trim()) {
+                return Err(Status::unauthenticated("invalid bearer token"));
+            }
+            Credentials::Token
+        } else if let Some(value) = metadata.get(SIGNATURE_HEADER) {
+            let signature = value
+                .to_str()
+                .ok()
+                .and_then(|v| hex::decode(v.trim_start_matches("0x")).ok())
+                .ok_or_else(|| {
+                    Status::unauthenticated(format!("{} must be hex", SIGNATURE_HEADER))
+                })?;
+            Credentials::Signature(signature)
+        } else {
+            return Ok(request);
+        };
+        request.extensions_mut().insert(credentials);
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    fn sign(key: &SigningKey, digest: &[u8; 32]) -> Vec<u8> {
+        let (sig, recid) = key.sign_prehash_recoverable(&eip191_hash(digest)).unwrap();
+        let mut bytes = sig.to_bytes().to_vec();
+        bytes.push(recid.to_byte() + 27);
+        bytes
+    }
+
+    fn key(byte: u8) -> SigningKey {
+        SigningKey::from_bytes(&[byte; 32].into()).unwrap()
+    }
+
+    #[test]
+    fn test_verify_sequencer() {
+        let sequencer = key(1);
+        let config = AuthConfig::new(vec![], vec![address(sequencer.verifying_key())]);
+        let digest = task_digest("1001", "1.0", b"task", &[b"payload".as_slice()]);
+
+        config
+            .verify_sequencer(&digest, &sign(&sequencer, &digest))
+            .unwrap();
+        assert!(config
+            .verify_sequencer(&digest, &sign(&key(2), &digest))
+            .is_err());
+
+        let other = task_digest("1001", "1.0", b"task", &[b"payload2".as_slice()]);
+        assert!(config
+            .verify_sequencer(&other, &sign(&sequencer, &digest))
+            .is_err());
+        assert!(config.verify_sequencer(&digest, &[0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn test_task_digest_is_unambiguous() {
+        assert_ne!(
+            task_digest("1", "01", b"", &[]),
+            task_digest("10", "1", b"", &[])
+        );
+        assert_ne!(
+            task_digest("1", "1", b"", &[b"ab".as_slice(), b"c".as_slice()]),
+            task_digest("1", "1", b"", &[b"a".as_slice(), b"bc".as_slice()])
+        );
+    }
+
+    #[test]
+    fn test_interceptor() {
+        let sequencer = key(1);
+        let config = Arc::new(AuthConfig::new(
+            vec!["secret".to_string()],
+            vec![address(sequencer.verifying_key())],
+        ));
+        let mut interceptor = AuthInterceptor::new(config.clone());
+        let digest = || task_digest("1001", "1.0", b"task", &[b"payload".as_slice()]);
+
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        let request = interceptor.call(request).unwrap();
+        config.authorize_task(&request, &[], digest).unwrap();
+
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer wrong".parse().unwrap());
+        assert!(interceptor.call(request).is_err());
+
+        let mut request = Request::new(());
+        let signature = hex::encode(sign(&sequencer, &digest()));
+        request
+            .metadata_mut()
+            .insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        let request = interceptor.call(request).unwrap();
+        config.authorize_task(&request, &[], digest).unwrap();
+        let err = config
+            .authorize_task(&request, &[], || task_digest("1001", "2.0", b"task", &[]))
+            .unwrap_err();
+        assert_eq!(Status::from(err).code(), tonic::Code::PermissionDenied);
+
+        let request = interceptor.call(Request::new(())).unwrap();
+        let err = config.authorize_task(&request, &[], digest).unwrap_err();
+        assert_eq!(Status::from(err).code(), tonic::Code::Unauthenticated);
+        let body_signature = sign(&sequencer, &digest());
+        config
+            .authorize_task(&request, &body_signature, digest)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_require_token() {
+        let sequencer = key(1);
+        let config = Arc::new(AuthConfig::new(
+            vec!["secret".to_string()],
+            vec![address(sequencer.verifying_key())],
+        ));
+        let mut interceptor = AuthInterceptor::new(config.clone());
+        let code = |request: &Request<()>| {
+            config
+                .require_token(request)
+                .map_err(|e| Status::from(e).code())
+        };
+
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        assert_eq!(code(&interceptor.call(request).unwrap()), Ok(()));
+
+        let request = interceptor.call(Request::new(())).unwrap();
+        assert_eq!(code(&request), Err(tonic::Code::Unauthenticated));
+
+        let mut request = Request::new(());
+        let digest = task_digest("1001", "1.0", b"task", &[]);
+        let signature = hex::encode(sign(&sequencer, &digest));
+        request
+            .metadata_mut()
+            .insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        let request = interceptor.call(request).unwrap();
+        assert_eq!(code(&request), Err(tonic::Code::PermissionDenied));
+    }
+}
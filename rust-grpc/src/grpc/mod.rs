@@ -1 +1,10 @@
 pub mod vm;
+pub mod vm_runtime;
+
+/// Encoded descriptors for `vm_runtime.proto`, for gRPC server reflection.
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/runtime_descriptor.bin"));
+
+/// Encoded descriptors for `wasm_runtime.proto`, for gRPC server reflection.
+pub const WASM_FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/wasm_runtime_descriptor.bin"));
@@ -9,13 +9,23 @@ pub struct NewProjectRequest {
     pub binary: ::prost::alloc::vec::Vec<u8>,
     #[prost(bytes = "vec", tag = "4")]
     pub metadata: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "5")]
+    pub replace: bool,
+    /// Signature of the upload digest by a project owner; required when the
+    /// server has project owners configured.
+    #[prost(bytes = "vec", tag = "6")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
-pub struct NewProjectResponse {}
+pub struct NewProjectResponse {
+    #[prost(uint64, tag = "1")]
+    pub preflight_cycles: u64,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExecuteTaskRequest {
     #[prost(string, tag = "1")]
     pub project_id: ::prost::alloc::string::String,
+    /// An exact version, "latest" or a semver range such as "^1.2".
     #[prost(string, tag = "2")]
     pub project_version: ::prost::alloc::string::String,
     #[prost(bytes = "vec", tag = "3")]
@@ -27,7 +37,132 @@ pub struct ExecuteTaskRequest {
 pub struct ExecuteTaskResponse {
     #[prost(bytes = "vec", tag = "1")]
     pub result: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, repeated, tag = "2")]
+    pub attempts: ::prost::alloc::vec::Vec<ProveAttempt>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub stdout: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub stderr: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "5")]
+    pub output_truncated: bool,
+    #[prost(bytes = "vec", tag = "6")]
+    pub profile: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "7")]
+    pub journal_json: ::prost::alloc::string::String,
+    /// The version projectVersion resolved to.
+    #[prost(string, tag = "8")]
+    pub resolved_version: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProveAttempt {
+    #[prost(uint32, tag = "1")]
+    pub attempt: u32,
+    #[prost(string, tag = "2")]
+    pub error_class: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub error: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "4")]
+    pub backoff_ms: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConvertReceiptRequest {
+    #[prost(string, tag = "1")]
+    pub project_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub project_version: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub receipt: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "4")]
+    pub receipt_type: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConvertReceiptResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub result: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "2")]
+    pub resolved_version: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyReceiptRequest {
+    #[prost(string, tag = "1")]
+    pub project_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub project_version: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub image_id: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub receipt: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyReceiptResponse {
+    #[prost(bool, tag = "1")]
+    pub valid: bool,
+    #[prost(string, tag = "2")]
+    pub receipt_type: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub journal: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "4")]
+    pub error: ::prost::alloc::string::String,
+    /// Empty when the image ID was given directly.
+    #[prost(string, tag = "5")]
+    pub resolved_version: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProjectInfo {
+    #[prost(string, tag = "1")]
+    pub project_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub project_version: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub image_id: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "4")]
+    pub elf_size: u64,
+    #[prost(bytes = "vec", tag = "5")]
+    pub metadata: ::prost::alloc::vec::Vec<u8>,
+    #[prost(int64, tag = "6")]
+    pub registered_at: i64,
+    #[prost(uint64, tag = "7")]
+    pub tasks_succeeded: u64,
+    #[prost(uint64, tag = "8")]
+    pub tasks_failed: u64,
+    #[prost(uint64, tag = "9")]
+    pub preflight_cycles: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListProjectsRequest {
+    #[prost(string, tag = "1")]
+    pub project_id: ::prost::alloc::string::String,
 }
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListProjectsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub projects: ::prost::alloc::vec::Vec<ProjectInfo>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetProjectRequest {
+    #[prost(string, tag = "1")]
+    pub project_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub project_version: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetProjectResponse {
+    #[prost(message, optional, tag = "1")]
+    pub project: ::core::option::Option<ProjectInfo>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteProjectRequest {
+    #[prost(string, tag = "1")]
+    pub project_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub project_version: ::prost::alloc::string::String,
+    /// Signature of the delete digest by a project owner; required when the
+    /// server has project owners configured.
+    #[prost(bytes = "vec", tag = "3")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DeleteProjectResponse {}
 /// Generated client implementations.
 pub mod vm_client {
     #![allow(
@@ -161,6 +296,111 @@ pub mod vm_client {
             req.extensions_mut().insert(GrpcMethod::new("vm.VM", "ExecuteTask"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn convert_receipt(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ConvertReceiptRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ConvertReceiptResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/vm.VM/ConvertReceipt");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("vm.VM", "ConvertReceipt"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn verify_receipt(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VerifyReceiptRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::VerifyReceiptResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/vm.VM/VerifyReceipt");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("vm.VM", "VerifyReceipt"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_projects(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListProjectsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListProjectsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/vm.VM/ListProjects");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("vm.VM", "ListProjects"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_project(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetProjectRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetProjectResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/vm.VM/GetProject");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("vm.VM", "GetProject"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn delete_project(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteProjectRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteProjectResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/vm.VM/DeleteProject");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("vm.VM", "DeleteProject"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -190,6 +430,41 @@ pub mod vm_server {
             tonic::Response<super::ExecuteTaskResponse>,
             tonic::Status,
         >;
+        async fn convert_receipt(
+            &self,
+            request: tonic::Request<super::ConvertReceiptRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ConvertReceiptResponse>,
+            tonic::Status,
+        >;
+        async fn verify_receipt(
+            &self,
+            request: tonic::Request<super::VerifyReceiptRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::VerifyReceiptResponse>,
+            tonic::Status,
+        >;
+        async fn list_projects(
+            &self,
+            request: tonic::Request<super::ListProjectsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListProjectsResponse>,
+            tonic::Status,
+        >;
+        async fn get_project(
+            &self,
+            request: tonic::Request<super::GetProjectRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetProjectResponse>,
+            tonic::Status,
+        >;
+        async fn delete_project(
+            &self,
+            request: tonic::Request<super::DeleteProjectRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteProjectResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct VmServer<T> {
@@ -353,6 +628,221 @@ pub mod vm_server {
                     };
                     Box::pin(fut)
                 }
+                "/vm.VM/ConvertReceipt" => {
+                    #[allow(non_camel_case_types)]
+                    struct ConvertReceiptSvc<T: Vm>(pub Arc<T>);
+                    impl<T: Vm> tonic::server::UnaryService<super::ConvertReceiptRequest>
+                    for ConvertReceiptSvc<T> {
+                        type Response = super::ConvertReceiptResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ConvertReceiptRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Vm>::convert_receipt(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ConvertReceiptSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/vm.VM/VerifyReceipt" => {
+                    #[allow(non_camel_case_types)]
+                    struct VerifyReceiptSvc<T: Vm>(pub Arc<T>);
+                    impl<T: Vm> tonic::server::UnaryService<super::VerifyReceiptRequest>
+                    for VerifyReceiptSvc<T> {
+                        type Response = super::VerifyReceiptResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VerifyReceiptRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Vm>::verify_receipt(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = VerifyReceiptSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/vm.VM/ListProjects" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListProjectsSvc<T: Vm>(pub Arc<T>);
+                    impl<T: Vm> tonic::server::UnaryService<super::ListProjectsRequest>
+                    for ListProjectsSvc<T> {
+                        type Response = super::ListProjectsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListProjectsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Vm>::list_projects(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListProjectsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/vm.VM/GetProject" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetProjectSvc<T: Vm>(pub Arc<T>);
+                    impl<T: Vm> tonic::server::UnaryService<super::GetProjectRequest>
+                    for GetProjectSvc<T> {
+                        type Response = super::GetProjectResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetProjectRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Vm>::get_project(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetProjectSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/vm.VM/DeleteProject" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteProjectSvc<T: Vm>(pub Arc<T>);
+                    impl<T: Vm> tonic::server::UnaryService<super::DeleteProjectRequest>
+                    for DeleteProjectSvc<T> {
+                        type Response = super::DeleteProjectResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteProjectRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Vm>::delete_project(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DeleteProjectSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         let mut response = http::Response::new(empty_body());
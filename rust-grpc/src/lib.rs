@@ -1 +1,3 @@
-pub mod grpc;
\ No newline at end of file
+pub mod auth;
+pub mod grpc;
+pub mod tls;
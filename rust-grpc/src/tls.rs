@@ -0,0 +1,441 @@
+//! TLS and mutual TLS for the VM servers, with certificates reloaded from disk
+//! on SIGHUP.
+//!
+//! tonic's own TLS config is fixed at startup, so connections are accepted and
+//! handshaken here and handed to `serve_with_incoming`.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::Connected;
+use tracing::{info, warn};
+
+/// Pending handshaken connections buffered for the server.
+const ACCEPT_BACKLOG: usize = 128;
+
+/// How long a client gets to complete the handshake before it is dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Certificate files, also the `[tls]` section of a server config file.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    #[serde(rename = "cert")]
+    pub cert_path: PathBuf,
+    #[serde(rename = "key")]
+    pub key_path: PathBuf,
+    /// CA bundle client certificates must chain to; enables mutual TLS.
+    #[serde(rename = "client_ca", default, skip_serializing_if = "Option::is_none")]
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Reads `TLS_CERT`, `TLS_KEY` and optional `TLS_CLIENT_CA`. Returns
+    /// `None` when `TLS_CERT` is unset, leaving the server on plaintext.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        let Ok(cert_path) = env::var("TLS_CERT") else {
+            return Ok(None);
+        };
+        let key_path =
+            env::var("TLS_KEY").map_err(|_| invalid("TLS_KEY must be set with TLS_CERT"))?;
+        Ok(Some(TlsConfig {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            client_ca_path: env::var("TLS_CLIENT_CA").ok().map(PathBuf::from),
+        }))
+    }
+
+    fn server_config(&self) -> io::Result<ServerConfig> {
+        let certs = load_certs(&self.cert_path)?;
+        let key = load_key(&self.key_path)?;
+        let builder = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| invalid(e.to_string()))?;
+
+        let builder = match &self.client_ca_path {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(path)? {
+                    roots
+                        .add(cert)
+                        .map_err(|e| invalid(format!("bad client CA: {}", e)))?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider())
+                        .build()
+                        .map_err(|e| invalid(format!("bad client CA: {}", e)))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(certs, key)
+            .map_err(|e| invalid(format!("bad server certificate: {}", e)))?;
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(config)
+    }
+}
+
+/// A TLS acceptor whose certificates can be swapped while serving. New
+/// connections use the latest config; established ones keep theirs.
+#[derive(Clone)]
+pub struct ReloadableTls {
+    config: TlsConfig,
+    acceptor: Arc<RwLock<tokio_rustls::TlsAcceptor>>,
+    handshake_timeout: Duration,
+}
+
+impl ReloadableTls {
+    pub fn new(config: TlsConfig) -> io::Result<Self> {
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config.server_config()?));
+        Ok(ReloadableTls {
+            config,
+            acceptor: Arc::new(RwLock::new(acceptor)),
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+        })
+    }
+
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Re-reads the certificate files. On error the previous config stays in
+    /// use.
+    pub fn reload(&self) -> io::Result<()> {
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(self.config.server_config()?));
+        *self.acceptor.write().unwrap() = acceptor;
+        Ok(())
+    }
+
+    /// Reloads the certificates whenever the process receives SIGHUP.
+    pub fn reload_on_sighup(&self) -> io::Result<()> {
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        let tls = self.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                match tls.reload() {
+                    Ok(()) => info!("TLS certificates reloaded"),
+                    Err(e) => warn!("TLS reload failed, keeping previous certificates: {}", e),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Accepts connections on `listener` and yields those that complete the
+    /// handshake. Failed or stalled handshakes are logged and dropped.
+    pub fn incoming(&self, listener: TcpListener) -> ReceiverStream<io::Result<TlsConn>> {
+        let (tx, rx) = mpsc::channel(ACCEPT_BACKLOG);
+        let tls = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, remote_addr) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+                let acceptor = tls.acceptor.read().unwrap().clone();
+                let conn_tx = tx.clone();
+                let handshake_timeout = tls.handshake_timeout;
+                // Handshake off the accept loop so a slow client cannot stall it.
+                tokio::spawn(async move {
+                    match tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = conn_tx
+                                .send(Ok(TlsConn {
+                                    stream,
+                                    remote_addr,
+                                }))
+                                .await;
+                        }
+                        Ok(Err(e)) => warn!("TLS handshake with {} failed: {}", remote_addr, e),
+                        Err(_) => warn!("TLS handshake with {} timed out", remote_addr),
+                    }
+                });
+                if tx.is_closed() {
+                    break;
+                }
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+}
+
+/// A server-side TLS connection.
+pub struct TlsConn {
+    stream: TlsStream<TcpStream>,
+    remote_addr: SocketAddr,
+}
+
+impl TlsConn {
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// The client's certificate chain, when mutual TLS is on.
+    pub fn peer_certs(&self) -> Option<Arc<Vec<CertificateDer<'static>>>> {
+        self.stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .map(|certs| Arc::new(certs.to_vec()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConnInfo {
+    pub remote_addr: SocketAddr,
+    pub peer_certs: Option<Arc<Vec<CertificateDer<'static>>>>,
+}
+
+impl Connected for TlsConn {
+    type ConnectInfo = TlsConnInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        TlsConnInfo {
+            remote_addr: self.remote_addr,
+            peer_certs: self.peer_certs(),
+        }
+    }
+}
+
+impl AsyncRead for TlsConn {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConn {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let file = fs::File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file)).collect::<io::Result<Vec<_>>>()?;
+    if certs.is_empty() {
+        return Err(invalid(format!("no certificates in {}", path.display())));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> io::Result<PrivateKeyDer<'static>> {
+    let file = fs::File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))?
+        .ok_or_else(|| invalid(format!("no private key in {}", path.display())))
+}
+
+// Pinned rather than taken from rustls' process default, which other
+// dependencies may leave ambiguous.
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls::pki_types::ServerName;
+    use rustls::ClientConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_stream::StreamExt;
+
+    struct Ca {
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    impl Ca {
+        fn new() -> Self {
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let key = KeyPair::generate().unwrap();
+            let cert = params.self_signed(&key).unwrap();
+            Ca { cert, key }
+        }
+
+        // Returns (cert chain PEM, key PEM) for a leaf valid for localhost.
+        fn issue(&self) -> (String, String) {
+            let params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+            let key = KeyPair::generate().unwrap();
+            let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+            (cert.pem(), key.serialize_pem())
+        }
+
+        fn roots(&self) -> RootCertStore {
+            let mut roots = RootCertStore::empty();
+            roots.add(self.cert.der().clone()).unwrap();
+            roots
+        }
+    }
+
+    fn write_server_files(dir: &tempfile::TempDir, ca: &Ca, client_ca: Option<&Ca>) -> TlsConfig {
+        let (cert, key) = ca.issue();
+        fs::write(dir.path().join("server.pem"), cert).unwrap();
+        fs::write(dir.path().join("server.key"), key).unwrap();
+        let client_ca_path = client_ca.map(|client_ca| {
+            let path = dir.path().join("client_ca.pem");
+            fs::write(&path, client_ca.cert.pem()).unwrap();
+            path
+        });
+        TlsConfig {
+            cert_path: dir.path().join("server.pem"),
+            key_path: dir.path().join("server.key"),
+            client_ca_path,
+        }
+    }
+
+    fn client(
+        roots: RootCertStore,
+        identity: Option<(String, String)>,
+    ) -> tokio_rustls::TlsConnector {
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let config = match identity {
+            Some((cert, key)) => {
+                let certs = rustls_pemfile::certs(&mut cert.as_bytes())
+                    .collect::<io::Result<Vec<_>>>()
+                    .unwrap();
+                let key = rustls_pemfile::private_key(&mut key.as_bytes())
+                    .unwrap()
+                    .unwrap();
+                builder.with_client_auth_cert(certs, key).unwrap()
+            }
+            None => builder.with_no_client_auth(),
+        };
+        tokio_rustls::TlsConnector::from(Arc::new(config))
+    }
+
+    async fn serve(tls: &ReloadableTls) -> (SocketAddr, ReceiverStream<io::Result<TlsConn>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        (addr, tls.incoming(listener))
+    }
+
+    async fn connect(
+        connector: &tokio_rustls::TlsConnector,
+        addr: SocketAddr,
+    ) -> io::Result<tokio_rustls::client::TlsStream<TcpStream>> {
+        let tcp = TcpStream::connect(addr).await?;
+        connector
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_tls_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old_ca, new_ca) = (Ca::new(), Ca::new());
+        let tls = ReloadableTls::new(write_server_files(&dir, &old_ca, None)).unwrap();
+        let (addr, mut incoming) = serve(&tls).await;
+
+        connect(&client(old_ca.roots(), None), addr).await.unwrap();
+        incoming.next().await.unwrap().unwrap();
+
+        write_server_files(&dir, &new_ca, None);
+        tls.reload().unwrap();
+        assert!(connect(&client(old_ca.roots(), None), addr).await.is_err());
+        connect(&client(new_ca.roots(), None), addr).await.unwrap();
+
+        // A broken file keeps the current certificates.
+        fs::write(dir.path().join("server.pem"), "garbage").unwrap();
+        assert!(tls.reload().is_err());
+        connect(&client(new_ca.roots(), None), addr).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handshake_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = Ca::new();
+        let tls = ReloadableTls::new(write_server_files(&dir, &ca, None))
+            .unwrap()
+            .with_handshake_timeout(Duration::from_millis(100));
+        let (addr, mut incoming) = serve(&tls).await;
+
+        // A client that never starts the handshake is disconnected.
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(5), idle.read(&mut buf))
+            .await
+            .unwrap();
+        assert!(!matches!(read, Ok(n) if n > 0));
+
+        connect(&client(ca.roots(), None), addr).await.unwrap();
+        incoming.next().await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server_ca, client_ca) = (Ca::new(), Ca::new());
+        let tls =
+            ReloadableTls::new(write_server_files(&dir, &server_ca, Some(&client_ca))).unwrap();
+        let (addr, mut incoming) = serve(&tls).await;
+
+        let mut stream = connect(&client(server_ca.roots(), Some(client_ca.issue())), addr)
+            .await
+            .unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let conn = incoming.next().await.unwrap().unwrap();
+        assert_eq!(conn.connect_info().peer_certs.unwrap().len(), 1);
+
+        // TLS 1.3 clients may finish before the server checks their
+        // certificate, so the rejection can show up as a failed read; either
+        // way no connection is accepted.
+        if let Ok(mut stream) = connect(&client(server_ca.roots(), None), addr).await {
+            let _ = stream.write_all(b"ping").await;
+            let mut buf = [0u8; 4];
+            assert!(!matches!(stream.read(&mut buf).await, Ok(n) if n > 0));
+        }
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), incoming.next())
+                .await
+                .is_err()
+        );
+    }
+}
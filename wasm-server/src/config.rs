@@ -0,0 +1,193 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+use rust_grpc::auth::AuthSettings;
+use rust_grpc::tls::TlsConfig;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen: String,
+    pub log: LogConfig,
+    pub limits: Limits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    pub auth: AuthSettings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracing: Option<TracingConfig>,
+    pub shutdown: ShutdownConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    // a tracing filter such as `info` or `wasm_server=debug,info`
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig { level: "info".to_string(), format: LogFormat::Text }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    // bytes of decompressed wasm accepted by Create
+    pub max_module_size: usize,
+    // bytes of datas, summed, accepted by Execute
+    pub max_task_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { max_module_size: 16 << 20, max_task_size: 4 << 20 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    // address of the prometheus /metrics endpoint
+    pub listen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TracingConfig {
+    // OTLP/gRPC collector, e.g. http://otel:4317
+    pub otlp_endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    // how long running executions get to finish after SIGTERM
+    pub grace_period_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        ShutdownConfig { grace_period_secs: 60 }
+    }
+}
+
+fn default_service_name() -> String {
+    "wasm-server".to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            listen: "0.0.0.0:4001".to_string(),
+            log: LogConfig::default(),
+            limits: Limits::default(),
+            tls: None,
+            auth: AuthSettings::default(),
+            metrics: None,
+            tracing: None,
+            shutdown: ShutdownConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    // file values first, then MAX_MODULE_SIZE, MAX_TASK_SIZE, TLS_*, AUTH_*, METRICS_LISTEN, OTEL_* and SHUTDOWN_GRACE_PERIOD_SECS env vars on top
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let mut config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).with_context(|| format!("failed to read config {}", path.display()))?;
+                toml::from_str(&text).with_context(|| format!("failed to parse config {}", path.display()))?
+            }
+            None => Config::default(),
+        };
+        parse_env("MAX_MODULE_SIZE", &mut config.limits.max_module_size)?;
+        parse_env("MAX_TASK_SIZE", &mut config.limits.max_task_size)?;
+        if let Some(tls) = TlsConfig::from_env()? {
+            config.tls = Some(tls);
+        }
+        config.auth.apply_env();
+        if let Ok(listen) = std::env::var("METRICS_LISTEN") {
+            config.metrics = Some(MetricsConfig { listen });
+        }
+        if let Ok(otlp_endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            let tracing = config.tracing.get_or_insert_with(|| TracingConfig { otlp_endpoint: String::new(), service_name: default_service_name() });
+            tracing.otlp_endpoint = otlp_endpoint;
+        }
+        if let (Some(tracing), Ok(name)) = (&mut config.tracing, std::env::var("OTEL_SERVICE_NAME")) {
+            tracing.service_name = name;
+        }
+        parse_env("SHUTDOWN_GRACE_PERIOD_SECS", &mut config.shutdown.grace_period_secs)?;
+        Ok(config)
+    }
+
+    pub fn display(&self) -> String {
+        let mut config = self.clone();
+        config.auth = config.auth.redacted();
+        toml::to_string(&config).unwrap_or_default()
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str, value: &mut T) -> anyhow::Result<()> {
+    if let Ok(v) = std::env::var(name) {
+        *value = v.parse().map_err(|_| anyhow::anyhow!("{} must be a number", name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_load() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            listen = "127.0.0.1:5000"
+
+            [log]
+            format = "json"
+
+            [limits]
+            max_module_size = 1024
+
+            [auth]
+            tokens = ["secret-token"]
+            "#
+        )
+        .unwrap();
+
+        std::env::set_var("MAX_TASK_SIZE", "2048");
+        let config = Config::load(Some(file.path())).unwrap();
+        std::env::remove_var("MAX_TASK_SIZE");
+        assert_eq!(config.listen, "127.0.0.1:5000");
+        assert_eq!(config.log, LogConfig { level: "info".to_string(), format: LogFormat::Json });
+        assert_eq!(config.limits, Limits { max_module_size: 1024, max_task_size: 2048 });
+
+        let shown = config.display();
+        assert!(!shown.contains("secret-token"), "{}", shown);
+        assert_eq!(toml::from_str::<Config>(&shown).unwrap().limits, config.limits);
+
+        assert!(Config::load(Some(Path::new("/nonexistent/wasm-server.toml"))).is_err());
+        write!(file, "unknown = 1").unwrap();
+        assert!(Config::load(Some(file.path())).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use rust_grpc::auth::{task_digest, AuthConfig, AuthError, Credentials};
+use rust_grpc::grpc::vm_runtime::ExecuteRequest;
+use tonic::Request;
+
+// Callers without a token must sign the task with an allowed sequencer key,
+// in sequencer_signature or the x-sequencer-signature header.
+// ExecuteRequest has no version, so it is signed as "".
+pub fn authorize_execute(config: &AuthConfig, request: &Request<ExecuteRequest>) -> Result<(), AuthError> {
+    if let Some(Credentials::Token) = request.extensions().get::<Credentials>() {
+        return Ok(());
+    }
+
+    let req = request.get_ref();
+    let body_signature = match req.sequencer_signature.as_str() {
+        "" => vec![],
+        signature => hex::decode(signature.trim_start_matches("0x")).map_err(|_| AuthError::Invalid("sequencer_signature must be hex".to_string()))?,
+    };
+    let digest = || {
+        let payloads: Vec<&[u8]> = req.datas.iter().map(String::as_bytes).collect();
+        task_digest(&req.project_id.to_string(), "", req.task_id.to_string().as_bytes(), &payloads)
+    };
+    config.authorize_task(request, &body_signature, digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+    use sha3::{Digest, Keccak256};
+    use tonic::Status;
+
+    fn key(byte: u8) -> SigningKey {
+        SigningKey::from_bytes(&[byte; 32].into()).unwrap()
+    }
+
+    fn address(key: &SigningKey) -> [u8; 20] {
+        let point = key.verifying_key().to_encoded_point(false);
+        Keccak256::digest(&point.as_bytes()[1..])[12..].try_into().unwrap()
+    }
+
+    // hex EIP-191 signature of the request's task digest
+    fn sign(key: &SigningKey, req: &ExecuteRequest) -> String {
+        let payloads: Vec<&[u8]> = req.datas.iter().map(String::as_bytes).collect();
+        let digest = task_digest(&req.project_id.to_string(), "", req.task_id.to_string().as_bytes(), &payloads);
+        let mut hasher = Keccak256::new();
+        hasher.update(b"\x19Ethereum Signed Message:\n32");
+        hasher.update(digest);
+        let (sig, recid) = key.sign_prehash_recoverable(&hasher.finalize()).unwrap();
+        let mut bytes = sig.to_bytes().to_vec();
+        bytes.push(recid.to_byte() + 27);
+        hex::encode(bytes)
+    }
+
+    fn execute_request(signature: &str) -> ExecuteRequest {
+        ExecuteRequest {
+            project_id: 10003,
+            task_id: 7,
+            client_id: "client".to_string(),
+            sequencer_signature: signature.to_string(),
+            datas: vec!["wasm log example".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_authorize_execute() {
+        let sequencer = key(1);
+        let config = AuthConfig::new(vec!["secret".to_string()], vec![address(&sequencer)]);
+        let code = |req: ExecuteRequest| authorize_execute(&config, &Request::new(req)).map_err(|e| Status::from(e).code());
+
+        let signed = sign(&sequencer, &execute_request(""));
+        assert_eq!(code(execute_request(&signed)), Ok(()));
+        assert_eq!(code(execute_request(&format!("0x{}", signed))), Ok(()));
+        assert_eq!(code(execute_request(&sign(&key(2), &execute_request("")))), Err(tonic::Code::PermissionDenied));
+        assert_eq!(code(execute_request("")), Err(tonic::Code::Unauthenticated));
+        assert_eq!(code(execute_request("test_sequencer_sign")), Err(tonic::Code::PermissionDenied));
+
+        let mut tampered = execute_request(&signed);
+        tampered.datas.push("extra".to_string());
+        assert_eq!(code(tampered), Err(tonic::Code::PermissionDenied));
+
+        // a bearer token, already checked by the interceptor, needs no signature
+        let mut request = Request::new(execute_request("test_sequencer_sign"));
+        request.extensions_mut().insert(Credentials::Token);
+        assert!(authorize_execute(&config, &request).is_ok());
+    }
+}
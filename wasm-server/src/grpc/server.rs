@@ -5,41 +5,83 @@ use std::{collections::HashMap, sync::Arc, i32::MAX};
 use serde_json::json;
 use tokio::sync::Mutex;
 use tonic::{Request, Response, Status};
+use tracing::{info, info_span, Span};
 use uuid::Uuid;
 use flate2::read::ZlibDecoder;
 use hex::FromHex;
 
 use rust_grpc::grpc::vm_runtime::{vm_runtime_server::VmRuntime, CreateRequest, CreateResponse, ExecuteRequest, ExecuteResponse};
 
+use rust_grpc::auth::AuthConfig;
+
+use crate::config::Limits;
+use crate::grpc::auth::authorize_execute;
+use crate::metrics;
 use crate::wasmtime::instance as wasm_instance;
 
 pub struct WasmtimeGrpcServer {
     instances_map: Arc<Mutex<HashMap<u64, wasm_instance::Instance>>>,
+    auth: Option<Arc<AuthConfig>>,
+    limits: Limits,
 }
 
 impl Default for WasmtimeGrpcServer {
     fn default() -> Self {
-        WasmtimeGrpcServer { instances_map: Arc::new(Mutex::new(HashMap::<u64, wasm_instance::Instance>::new())) }
+        WasmtimeGrpcServer {
+            instances_map: Arc::new(Mutex::new(HashMap::<u64, wasm_instance::Instance>::new())),
+            auth: None,
+            limits: Limits::default(),
+        }
+    }
+}
+
+impl WasmtimeGrpcServer {
+    pub fn with_auth(mut self, config: AuthConfig) -> Self {
+        self.auth = Some(Arc::new(config));
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn auth(&self) -> Option<Arc<AuthConfig>> {
+        self.auth.clone()
     }
 }
 
 #[tonic::async_trait]
 impl VmRuntime for WasmtimeGrpcServer {
     async fn create(&self, request: Request<CreateRequest>) -> Result<Response<CreateResponse>, Status> {
-        println!("wasm instance create...");
+        info!("wasm instance create...");
+        // there is no task to sign, so only a bearer token lets a module in
+        if let Some(auth) = &self.auth {
+            auth.require_token(&request)?;
+        }
         let request = request.into_inner();
 
         let project = request.project_id;
+        Span::current().record("project_id", project);
         let content = request.content;
-        let compressed_data = Vec::from_hex(content).unwrap();
-        let mut decoder = ZlibDecoder::new(&compressed_data[..]);
-        let mut content = Vec::new();
-        decoder.read_to_end(&mut content)?;
+        let max_module_size = self.limits.max_module_size;
+        let content = info_span!("decompress").in_scope(|| -> Result<Vec<u8>, Status> {
+            let compressed_data = Vec::from_hex(content).map_err(|_| Status::invalid_argument("content must be hex"))?;
+            let decoder = ZlibDecoder::new(&compressed_data[..]);
+            let mut content = Vec::new();
+            decoder.take(max_module_size as u64 + 1).read_to_end(&mut content)?;
+            if content.len() > max_module_size {
+                return Err(Status::invalid_argument(format!("module exceeds max_module_size of {} bytes", max_module_size)));
+            }
+            Ok(content)
+        })?;
         // let exp_param = request.exp_param;
 
 
         let id = Uuid::new_v4();
-        let instance = wasm_instance::new_instance_by_code(id, content).unwrap();
+        let instance = info_span!("compile")
+            .in_scope(|| wasm_instance::new_instance_by_code(id, content))
+            .map_err(|e| Status::invalid_argument(format!("invalid wasm module: {}", e)))?;
 
         let mut map = self.instances_map.lock().await;
         if let Some(_) = map.get(&project) {
@@ -47,14 +89,33 @@ impl VmRuntime for WasmtimeGrpcServer {
         }
 
         map.insert(project, instance);
+        metrics::PROJECTS.set(map.len() as i64);
         
         Ok(Response::new(CreateResponse {}))
     }
 
     async fn execute(&self, request: Request<ExecuteRequest>) -> Result<Response<ExecuteResponse>, Status> {
-        println!("wasm instance execute...");
+        info!("wasm instance execute...");
+        Span::current().record("project_id", request.get_ref().project_id);
+        Span::current().record("task_id", request.get_ref().task_id);
+        if let Some(auth) = &self.auth {
+            authorize_execute(auth, &request)?;
+        }
         let request = request.into_inner();
+        let project_id = request.project_id.to_string();
+
+        let queued = metrics::Queued::new(&project_id);
+        let start = std::time::Instant::now();
+        let result = self.run(request).await;
+        metrics::EXECUTION_DURATION.with_label_values(&[&project_id]).observe(start.elapsed().as_secs_f64());
+        drop(queued);
+        metrics::record_task(&project_id, &result);
+        result
+    }
+}
 
+impl WasmtimeGrpcServer {
+    async fn run(&self, request: ExecuteRequest) -> Result<Response<ExecuteResponse>, Status> {
         let project_id = request.project_id;
         let task_id = request.task_id;
         let client_id = request.client_id;
@@ -64,6 +125,10 @@ impl VmRuntime for WasmtimeGrpcServer {
         if datas.len() == 0 {
             return Err(Status::invalid_argument("need datas"))
         }
+        let task_size: usize = datas.iter().map(String::len).sum();
+        if task_size > self.limits.max_task_size {
+            return Err(Status::invalid_argument(format!("datas exceed max_task_size of {} bytes", self.limits.max_task_size)));
+        }
 
         let mut map = self.instances_map.lock().await;
         let instance = match map.get_mut(&project_id) {
@@ -85,6 +150,7 @@ impl VmRuntime for WasmtimeGrpcServer {
             res.insert(rid, serde_json::to_string(&map).unwrap().as_bytes().to_vec());
         }
 
+        let instantiate = info_span!("instantiate").entered();
         match instance.export_funcs.rt.instantiate() {
             Ok(_) => {},
             Err(_) => {
@@ -93,6 +159,8 @@ impl VmRuntime for WasmtimeGrpcServer {
             },
         };
 
+        drop(instantiate);
+        let _execute = info_span!("execute").entered();
         let result = match instance.export_funcs.rt.call("start", rid) {
             Ok(result) => result,
             Err(_) => {
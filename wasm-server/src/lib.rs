@@ -1,18 +1,92 @@
+use std::time::Duration;
+
+use config::Config;
 use grpc::server::WasmtimeGrpcServer;
+use rust_grpc::auth::AuthInterceptor;
 use rust_grpc::grpc::vm_runtime::vm_runtime_server::VmRuntimeServer;
+use rust_grpc::grpc::WASM_FILE_DESCRIPTOR_SET;
+use rust_grpc::tls::ReloadableTls;
+use tokio::net::TcpListener;
 use tonic::transport::Server;
 
+pub mod config;
 mod grpc;
+mod metrics;
+mod telemetry;
 #[cfg(test)]
 mod tests;
 mod wasmtime;
 
-pub async fn start_grpc_server(addr: &str) {
-    let addr = addr.parse().unwrap();
-    let wasm_grpc_server = WasmtimeGrpcServer::default();
+pub async fn start_grpc_server(config: Config) {
+    let addr: std::net::SocketAddr = config.listen.parse().unwrap();
+    telemetry::init(&config.log, config.tracing.as_ref()).unwrap();
+    let mut wasm_grpc_server = WasmtimeGrpcServer::default().with_limits(config.limits.clone());
+    if let Some(auth) = config.auth.build().unwrap() {
+        wasm_grpc_server = wasm_grpc_server.with_auth(auth);
+    }
+
+    if let Some(metrics) = &config.metrics {
+        let metrics_addr: std::net::SocketAddr = metrics.listen.parse().unwrap();
+        tracing::info!("serving metrics on {}", metrics_addr);
+        tokio::spawn(metrics::serve(metrics_addr));
+    }
+
+    // wasmtime runs in-process, so the runtime is ready as soon as it is served
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter.set_serving::<VmRuntimeServer<WasmtimeGrpcServer>>().await;
+
+    // on SIGTERM report NOT_SERVING and stop accepting connections, giving running executions the grace period
+    let grace_period = Duration::from_secs(config.shutdown.grace_period_secs);
+    let (signalled_tx, signalled_rx) = tokio::sync::oneshot::channel();
+    let on_signal = async move {
+        shutdown_signal().await;
+        tracing::info!("shutting down");
+        health_reporter.set_not_serving::<VmRuntimeServer<WasmtimeGrpcServer>>().await;
+        health_reporter.set_service_status("", tonic_health::ServingStatus::NotServing).await;
+        let _ = signalled_tx.send(());
+    };
+    let expired = async move {
+        if signalled_rx.await.is_err() {
+            return std::future::pending().await;
+        }
+        tokio::time::sleep(grace_period).await;
+        tracing::warn!("grace period expired, abandoning running executions");
+    };
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(WASM_FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()
+        .unwrap();
+
+    let mut builder = Server::builder().trace_fn(telemetry::request_span);
+    let router = builder.add_service(health_service).add_service(reflection);
+    let router = match wasm_grpc_server.auth() {
+        Some(auth) => router.add_service(VmRuntimeServer::with_interceptor(wasm_grpc_server, AuthInterceptor::new(auth))),
+        None => router.add_service(VmRuntimeServer::new(wasm_grpc_server)),
+    };
+    let serve = async move {
+        match config.tls {
+            Some(config) => {
+                let tls = ReloadableTls::new(config).unwrap();
+                tls.reload_on_sighup().unwrap();
+                let listener = TcpListener::bind(addr).await.unwrap();
+                router.serve_with_incoming_shutdown(tls.incoming(listener), on_signal).await.unwrap();
+            }
+            None => router.serve_with_shutdown(addr, on_signal).await.unwrap(),
+        }
+    };
+    tokio::select! {
+        _ = serve => {}
+        _ = expired => {}
+    }
+    telemetry::shutdown();
+}
 
-    Server::builder()
-        .add_service(VmRuntimeServer::new(wasm_grpc_server))
-        .serve(addr)
-        .await.unwrap();
+// resolves on SIGTERM or ctrl-c
+async fn shutdown_signal() {
+    let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).unwrap();
+    tokio::select! {
+        _ = term.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
 }
@@ -1,8 +1,49 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+use wasm_server::config::{Config, LogFormat};
 use wasm_server::start_grpc_server;
 
- #[tokio::main]
- async fn main() {
+#[derive(Parser)]
+#[command(version, about = "wasm VM gRPC server")]
+struct Cli {
+    /// TOML config file, env vars override its values
+    #[arg(short, long, env = "WASM_SERVER_CONFIG")]
+    config: Option<PathBuf>,
+    #[arg(long, env = "LISTEN_ADDR")]
+    listen: Option<String>,
+    /// log filter, e.g. `info` or `wasm_server=debug`
+    #[arg(long, env = "LOG_LEVEL")]
+    log_level: Option<String>,
+    #[arg(long, env = "LOG_FORMAT", value_enum)]
+    log_format: Option<LogFormat>,
+    /// print the effective config and exit
+    #[arg(long)]
+    print_config: bool,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let mut config = Config::load(cli.config.as_deref()).unwrap();
+    if let Some(listen) = cli.listen {
+        config.listen = listen;
+    }
+    if let Some(level) = cli.log_level {
+        config.log.level = level;
+    }
+    if let Some(format) = cli.log_format {
+        config.log.format = format;
+    }
+    println!("effective config:\n{}", config.display());
+    if cli.print_config {
+        return;
+    }
+
     // start grpc server
     println!("{}", "start grpc server...");
-    start_grpc_server("0.0.0.0:4001").await;
- }
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(start_grpc_server(config));
+    // don't wait on executions still running past the grace period
+    runtime.shutdown_timeout(Duration::ZERO);
+}
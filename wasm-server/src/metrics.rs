@@ -0,0 +1,97 @@
+use std::net::SocketAddr;
+
+use axum::{routing::get, Router};
+use lazy_static::lazy_static;
+use prometheus::{register_histogram_vec, register_int_counter_vec, register_int_gauge, register_int_gauge_vec, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder};
+use tokio::net::TcpListener;
+use tonic::Status;
+
+lazy_static! {
+    pub static ref TASKS: IntCounterVec = register_int_counter_vec!("wasm_tasks_total", "Finished tasks, by project and outcome (ok or the gRPC code).", &["project_id", "outcome"]).unwrap();
+    pub static ref EXECUTION_DURATION: HistogramVec = register_histogram_vec!("wasm_execution_duration_seconds", "Time spent running a task in wasmtime.", &["project_id"]).unwrap();
+    // execute holds the instance map lock, so tasks wait here behind each other
+    pub static ref TASK_QUEUE_DEPTH: IntGaugeVec = register_int_gauge_vec!("wasm_task_queue_depth", "Tasks accepted and not yet finished.", &["project_id"]).unwrap();
+    pub static ref PROJECTS: IntGauge = register_int_gauge!("wasm_projects", "Created project instances.").unwrap();
+}
+
+pub struct Queued(IntGauge);
+
+impl Queued {
+    pub fn new(project_id: &str) -> Self {
+        let gauge = TASK_QUEUE_DEPTH.with_label_values(&[project_id]);
+        gauge.inc();
+        Queued(gauge)
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+pub fn record_task<T>(project_id: &str, result: &Result<T, Status>) {
+    let outcome = match result {
+        Ok(_) => "ok".to_string(),
+        Err(status) => format!("{:?}", status.code()),
+    };
+    TASKS.with_label_values(&[project_id, &outcome]).inc();
+}
+
+pub fn render() -> String {
+    TextEncoder::new().encode_to_string(&prometheus::gather()).unwrap_or_default()
+}
+
+pub async fn serve(addr: SocketAddr) {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::warn!("metrics endpoint failed: {}", e);
+            return;
+        }
+    };
+    serve_on(listener).await;
+}
+
+async fn serve_on(listener: TcpListener) {
+    let app = Router::new().route("/metrics", get(|| async { render() }));
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::warn!("metrics endpoint failed: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn fetch(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        record_task("metrics-test", &Ok::<_, Status>(()));
+        record_task("metrics-test", &Err::<(), _>(Status::not_found("no project")));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_on(listener));
+
+        let response = fetch(addr, "/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        for outcome in ["ok", "NotFound"] {
+            let line = format!("wasm_tasks_total{{outcome=\"{}\",project_id=\"metrics-test\"}} 1", outcome);
+            assert!(response.contains(&line), "missing {} in\n{}", line, response);
+        }
+
+        let response = fetch(addr, "/other").await;
+        assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+    }
+}
@@ -0,0 +1,69 @@
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Config as TraceConfig, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tonic::codegen::http;
+use tracing::{field, info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{LogConfig, LogFormat, TracingConfig};
+
+// logs filtered by [log] level, spans exported over OTLP when configured
+pub fn init(log: &LogConfig, tracing: Option<&TracingConfig>) -> anyhow::Result<()> {
+    let fmt = match log.format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+    };
+
+    let provider = match tracing {
+        Some(config) => {
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            let provider = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(&config.otlp_endpoint))
+                .with_trace_config(TraceConfig::default().with_resource(Resource::new(vec![KeyValue::new("service.name", config.service_name.clone())])))
+                .install_batch(runtime::Tokio)?;
+            global::set_tracer_provider(provider.clone());
+            Some::<TracerProvider>(provider)
+        }
+        None => None,
+    };
+    let otel = provider.as_ref().map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("wasm-server")));
+
+    tracing_subscriber::registry()
+        .with(fmt)
+        .with(otel)
+        .with(EnvFilter::try_new(&log.level)?)
+        .try_init()?;
+    Ok(())
+}
+
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+// root span per call, parented to the caller's W3C traceparent
+pub fn request_span(req: &http::Request<()>) -> Span {
+    let span = info_span!("wasm_server", otel.name = req.uri().path(), project_id = field::Empty, task_id = field::Empty);
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(req.headers())));
+    span.set_parent(parent);
+    span
+}
+
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
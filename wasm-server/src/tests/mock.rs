@@ -1,5 +1,6 @@
 use std::{sync::atomic::{AtomicBool, Ordering}, thread, time::Duration};
 
+use crate::config::Config;
 use crate::start_grpc_server;
 
 pub struct Server {
@@ -17,7 +18,8 @@ impl Server {
         if !self.started.load(Ordering::Relaxed) {
             thread::spawn(move || {
                 let rt = tokio::runtime::Runtime::new().expect("runtime starts");
-                rt.spawn(start_grpc_server("0.0.0.0:14003"));
+                let config = Config { listen: "0.0.0.0:14003".to_string(), ..Config::default() };
+                rt.spawn(start_grpc_server(config));
                 loop {
                     thread::sleep(Duration::from_millis(100_000));
                 }
@@ -1,9 +1,10 @@
-use std::{fs, sync::RwLock};
+use std::{fs, io::Write, sync::RwLock};
 
 use rust_grpc::grpc::vm_runtime::{vm_runtime_client::VmRuntimeClient, CreateRequest, ExecuteRequest};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tonic::{transport::Channel, Request};
+use tonic::{transport::Channel, Code, Request};
+use flate2::{write::ZlibEncoder, Compression};
 use lazy_static::lazy_static;
 
 use crate::tests::mock::Server;
@@ -185,4 +186,39 @@ async fn test_executor_failed_e2e() {
             assert_eq!("no project", err.message())
         }
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_create_invalid_module_e2e() {
+    init_real_server().await;
+
+    let channel = Channel::from_static("http://127.0.0.1:14003")
+        .connect()
+        .await
+        .unwrap();
+    let mut client = VmRuntimeClient::new(channel);
+
+    // valid hex and zlib, but not a wasm module
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"not a wasm module").unwrap();
+    let content = hex::encode(encoder.finish().unwrap());
+
+    let req = Request::new(CreateRequest {
+        project_id: 10005,
+        content,
+        exp_param: "".to_string(),
+    });
+    let err = client.create(req).await.unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    // the rejected module was not registered
+    let req = Request::new(ExecuteRequest {
+        project_id: 10005,
+        task_id: 0u64,
+        client_id: "test_client_id".to_string(),
+        sequencer_signature: "test_sequencer_sign".to_string(),
+        datas: vec!["wasm log example".to_string()],
+    });
+    let err = client.execute(req).await.unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
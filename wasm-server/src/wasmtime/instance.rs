@@ -86,9 +86,9 @@ impl ABILinker for ExportFuncs {
         self.rt.linker.as_mut().unwrap().func_wrap("env", "ws_log", ExportFuncs::log).unwrap();
         // self.rt.linker.as_mut().unwrap().func_wrap("env", "ws_get_data", ExportFuncs::get_data).unwrap();
         self.rt.linker.as_mut().unwrap().func_wrap("env", "ws_get_data", move |mut caller: Caller<'_, WasiCtx>, rid: i32, vm_add_ptr: i32, vm_size_ptr: i32| -> i32 {
-            println!("log: rid {}", rid);
-            println!("log: add_ptr {}", vm_add_ptr);
-            println!("log: size_ptr {}", vm_size_ptr);
+            tracing::debug!("log: rid {}", rid);
+            tracing::debug!("log: add_ptr {}", vm_add_ptr);
+            tracing::debug!("log: size_ptr {}", vm_size_ptr);
 
             // let handle = thread::spawn(|| { res.lock().await };
             // let res = handle.join();
@@ -105,14 +105,14 @@ impl ABILinker for ExportFuncs {
             let alloc_func = match caller.get_export("alloc") {
                 Some(Extern::Func(func)) => func,
                 _ => {
-                    println!("expected a function export named 'alloc'");
+                    tracing::warn!("expected a function export named 'alloc'");
                     return 1;
                 },
             };
             let alloc_func_typed = match alloc_func.typed::<i32, i32>(&caller) {
                 Ok(func) => func,
                 Err(_) => {
-                    println!("function 'alloc' has a wrong type");
+                    tracing::warn!("function 'alloc' has a wrong type");
                     return 1;
                 },
             };
@@ -122,13 +122,13 @@ impl ABILinker for ExportFuncs {
             let memory = match caller.get_export("memory") {
                 Some(Extern::Memory(mem)) => mem,
                 _ => {
-                    println!("failed to find host memory");
+                    tracing::warn!("failed to find host memory");
                     return 1;
                 }
             };
     
             let pointer = unsafe { memory.data_ptr(&caller).add(mem_ptr as usize) };
-            println!("pointer is {:?}", pointer);
+            tracing::debug!("pointer is {:?}", pointer);
             unsafe {
                 copy(data.as_ptr(), pointer, len);
             }
@@ -140,7 +140,7 @@ impl ABILinker for ExportFuncs {
     
             match memory.write(&mut caller, offset, &addr_as_bytes) {
                 Ok(_) => {}
-                _ => println!("failed to write add_ptr to host memory"),
+                _ => tracing::warn!("failed to write add_ptr to host memory"),
             };
     
             let offset = vm_size_ptr as u32 as usize;
@@ -149,7 +149,7 @@ impl ABILinker for ExportFuncs {
             };
             match memory.write(&mut caller, offset, &addr_as_bytes) {
                 Ok(_) => {}
-                _ => println!("failed to write size_ptr to host memory"),
+                _ => tracing::warn!("failed to write size_ptr to host memory"),
             };
             0
         }).unwrap();
@@ -173,14 +173,14 @@ impl ExportFuncs {
     // }
 
     fn log(mut caller: Caller<'_, WasiCtx>, log_level: i32, ptr: i32, size: i32) -> i32 {
-        println!("log: logLevel {}", log_level);
-        println!("log: ptr {}", ptr);
-        println!("log: size {}", size);
+        tracing::debug!("log: logLevel {}", log_level);
+        tracing::debug!("log: ptr {}", ptr);
+        tracing::debug!("log: size {}", size);
 
         let memory = match caller.get_export("memory") {
             Some(Extern::Memory(mem)) => mem,
             _ => {
-                println!("failed to find host memory");
+                tracing::warn!("failed to find host memory");
                 return 1;
             }
         };
@@ -192,15 +192,15 @@ impl ExportFuncs {
             String::from_utf8(memory.data(&caller)[offset..][..size as usize]
             .to_vec()).unwrap()
         };
-        println!("Result: {}", byte3);
+        tracing::info!("guest log: {}", byte3);
         
         0
     }
 
     fn get_data(mut caller: Caller<'_, WasiCtx>, rid: i32, vm_add_ptr: i32, vm_size_ptr: i32) -> i32 {
-        println!("log: rid {}", rid);
-        println!("log: add_ptr {}", vm_add_ptr);
-        println!("log: size_ptr {}", vm_size_ptr);
+        tracing::debug!("log: rid {}", rid);
+        tracing::debug!("log: add_ptr {}", vm_add_ptr);
+        tracing::debug!("log: size_ptr {}", vm_size_ptr);
         // let data = self.res.get(rid);
         let data = String::from("The input string");
     
@@ -210,21 +210,21 @@ impl ExportFuncs {
         let alloc_func = match caller.get_export("alloc") {
             Some(Extern::Func(func)) => func,
             _ => {
-                println!("expected a function export named 'alloc'");
+                tracing::warn!("expected a function export named 'alloc'");
                 return 1;
             },
         };
         let alloc_func_typed = match alloc_func.typed::<i32, i32>(&caller) {
             Ok(func) => func,
             Err(_) => {
-                println!("function 'alloc' has a wrong type");
+                tracing::warn!("function 'alloc' has a wrong type");
                 return 1;
             },
         };
 
         // let alloc_func_typed: TypedFunc<u32, u32> = alloc_func.typed(caller).unwrap();
         let mem_ptr = alloc_func_typed.call(&mut caller.as_context_mut(), len.try_into().unwrap()).unwrap();
-        println!("mem_ptr is {}", mem_ptr);
+        tracing::debug!("mem_ptr is {}", mem_ptr);
 
         // let alloc_func_typed = alloc_func.get_typed_func::<u32, u32>();
         // let result = alloc_func_typed.call(size)?;
@@ -232,45 +232,45 @@ impl ExportFuncs {
         let memory = match caller.get_export("memory") {
             Some(Extern::Memory(mem)) => mem,
             _ => {
-                println!("failed to find host memory");
+                tracing::warn!("failed to find host memory");
                 return 1;
             }
         };
 
         let pointer = unsafe { memory.data_ptr(&caller).add(mem_ptr as usize) };
-        println!("pointer is {:?}", pointer);
+        tracing::debug!("pointer is {:?}", pointer);
         unsafe {
             let bytes = data.as_bytes();
             copy(bytes.as_ptr(), pointer, bytes.len());
 
             let byte3 = String::from_utf8(memory.data(&caller)[mem_ptr as usize..][..len as usize]
                 .to_vec()).unwrap();
-            println!("memeory write is : {}", byte3);
+            tracing::debug!("memeory write is : {}", byte3);
         }
         // _ = put_uint32_le( memory.data_mut( &caller), ptr, pointer as u32).unwrap();
         
 
        
         let offset = vm_add_ptr as u32 as usize;
-        println!("add_ptr is {:?}", offset);
+        tracing::debug!("add_ptr is {:?}", offset);
         let addr_as_bytes: [u8; std::mem::size_of::<usize>()] = unsafe {
             std::mem::transmute(mem_ptr as usize)
         };
-        println!("pointer u8 is {:?}", addr_as_bytes);
+        tracing::debug!("pointer u8 is {:?}", addr_as_bytes);
 
         match memory.write(&mut caller, offset, &addr_as_bytes) {
             Ok(_) => {}
-            _ => println!("failed to write add_ptr to host memory"),
+            _ => tracing::warn!("failed to write add_ptr to host memory"),
         };
 
         let offset = vm_size_ptr as u32 as usize;
-        println!("add_ptr is {:?}", offset);
+        tracing::debug!("add_ptr is {:?}", offset);
         let addr_as_bytes: [u8; std::mem::size_of::<usize>()] = unsafe {
             std::mem::transmute(len)
         };
         match memory.write(&mut caller, offset, &addr_as_bytes) {
             Ok(_) => {}
-            _ => println!("failed to write size_ptr to host memory"),
+            _ => tracing::warn!("failed to write size_ptr to host memory"),
         };
         0
     }
@@ -288,7 +288,7 @@ pub fn new_instance_by_code(id: uuid::Uuid, code: Vec<u8>) -> Result<Instance> {
     let mut instance = Instance { id, export_funcs };
 
     instance.export_funcs.link_abi();
-    let _ = instance.export_funcs.rt.creat(code);
+    instance.export_funcs.rt.creat(code)?;
 
     return anyhow::Ok(instance)
 }
\ No newline at end of file